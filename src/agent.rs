@@ -1,62 +1,376 @@
+use std::{fmt, str::FromStr};
+
 use euclid::Angle;
 
-use crate::environment::*;
+use crate::{environment::*, rules::GameMode};
 
 type RunStretch = euclid::default::Vector2D<f32>;
-/// How far a player can reach to tag another player
-const ARM_LENGTH: PlayerDistance = 1.;
-/// How far a player can run each step
-const MAX_SPEED: PlayerDistance = 2.;
+
+/// A strategy for playing the game, one agent controls one player
+pub trait Agent: Send + fmt::Debug {
+    /// Decide what action to take on this step based on looking at the environment
+    fn act(&mut self, player_id: PlayerId, environment: &TagEnvironment)
+        -> Result<TagPlayerAction>;
+}
 
 /// Logic and internal state for the player agent
-#[derive(Debug)]
-pub struct TagPlayerAgent;
+#[derive(Debug, Default)]
+pub struct TagPlayerAgent {
+    pub params: AgentParams,
+}
 
-impl TagPlayerAgent {
-    /// Decide what action to take on this step based on looking at the environment
-    pub fn act(
+/// The constants the player agent plays by, which can be tuned
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AgentParams {
+    /// Fraction of the maximum speed to run at
+    pub speed: f32,
+    /// How far from the edge of the play area to keep when turning along it
+    pub edge_margin: f32,
+    /// When fleeing, the direction away from each chaser is weighted by the distance to them raised to minus this
+    /// power, so the higher it is the more the closer chasers matter
+    pub flee_power: f32,
+    /// Degrees to turn from the direction directly away from the chasers when fleeing
+    pub flee_angle: f32,
+    /// How many times closer than the closest threat a frozen team mate has to be to go and rescue them
+    pub rescue_caution: f32,
+}
+
+impl AgentParams {
+    /// The name of each parameter, in the order of the parameter vector
+    pub const NAMES: [&'static str; 5] = [
+        "speed",
+        "edge_margin",
+        "flee_power",
+        "flee_angle",
+        "rescue_caution",
+    ];
+    /// The range each parameter can be tuned within, in the order of the parameter vector
+    pub const BOUNDS: [(f32, f32); 5] = [(0.5, 1.), (0.01, 3.), (0., 4.), (-90., 90.), (0.25, 4.)];
+
+    /// The parameters as a vector of numbers, for tuning
+    pub fn to_vector(self) -> [f32; 5] {
+        [
+            self.speed,
+            self.edge_margin,
+            self.flee_power,
+            self.flee_angle,
+            self.rescue_caution,
+        ]
+    }
+
+    pub fn from_vector(
+        [speed, edge_margin, flee_power, flee_angle, rescue_caution]: [f32; 5],
+    ) -> Self {
+        Self {
+            speed,
+            edge_margin,
+            flee_power,
+            flee_angle,
+            rescue_caution,
+        }
+    }
+}
+
+impl Default for AgentParams {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            edge_margin: 0.1,
+            flee_power: 2.,
+            flee_angle: 0.,
+            rescue_caution: 1.,
+        }
+    }
+}
+
+/// One `name = value` line per parameter, the format parameters are saved in
+impl fmt::Display for AgentParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in Self::NAMES.iter().zip(self.to_vector()) {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `name = value` lines, any parameters left out keep their default value
+impl FromStr for AgentParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut vector = Self::default().to_vector();
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Expected 'name = value' but found '{}'", line))?;
+            let index = Self::NAMES
+                .iter()
+                .position(|n| *n == name.trim())
+                .ok_or_else(|| format!("Unknown parameter '{}'", name.trim()))?;
+            vector[index] = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid value for {} '{}'", name.trim(), value.trim()))?;
+        }
+        Ok(Self::from_vector(vector))
+    }
+}
+
+impl Agent for TagPlayerAgent {
+    fn act(
         &mut self,
-        player_id: usize,
+        player_id: PlayerId,
         environment: &TagEnvironment,
     ) -> Result<TagPlayerAction> {
         let TagPlayerVisibleState {
-            position,
+            position: _,
             status: tagged_by,
+            team,
         } = environment.get_state(player_id);
 
-        let action = if tagged_by.is_it() {
-            let (closest_player, sq_distance) =
-                environment.closest_player_except(player_id, (*tagged_by).into())?;
-            if sq_distance < ARM_LENGTH * ARM_LENGTH {
-                TagPlayerAction::Tag {
-                    player_id: closest_player,
-                }
-            } else {
-                let vector = environment.get_state(closest_player).position - *position;
-                TagPlayerAction::Run {
-                    stretch: RunStretch::from_angle_and_length(
-                        vector.angle_from_x_axis(),
-                        MAX_SPEED,
-                    ),
+        let action = if tagged_by.is_frozen() {
+            TagPlayerAction::Run {
+                stretch: RunStretch::zero(),
+            }
+        } else if environment.rules().mode == GameMode::Teams {
+            let is_opponent =
+                |_, state: &TagPlayerVisibleState| state.team != *team && !state.is_frozen();
+            match environment.closest_player_where(player_id, is_opponent) {
+                Some(closest_opponent) if closest_opponent.1 < reach_squared(environment) => {
+                    chase(player_id, environment, &self.params, closest_opponent)
                 }
+                Some(closest_opponent) => rescue(player_id, environment, &self.params, is_opponent)
+                    .unwrap_or_else(|| {
+                        chase(player_id, environment, &self.params, closest_opponent)
+                    }),
+                // every opponent is captured, team mates can still be rescued until the game ends
+                None => rescue(player_id, environment, &self.params, is_opponent).unwrap_or(
+                    TagPlayerAction::Run {
+                        stretch: RunStretch::zero(),
+                    },
+                ),
             }
+        } else if tagged_by.is_it() {
+            chase_closest_target(player_id, environment, &self.params)
         } else {
-            let it = environment.get_it();
-            let vector = it.position - *position;
-            let mut angle = vector.angle_from_x_axis();
-            if !angle.is_finite() {
-                angle = Angle::radians(0.);
+            (environment.rules().mode == GameMode::Freeze)
+                .then(|| {
+                    rescue(player_id, environment, &self.params, |_, state| {
+                        state.is_it()
+                    })
+                })
+                .flatten()
+                .unwrap_or_else(|| flee(player_id, environment, &self.params))
+        };
+        Ok(action)
+    }
+}
+
+/// A simpler strategy to compare against: chasers go for the closest player they can tag, runners run straight away
+/// from the closest chaser and nobody rescues anyone
+#[derive(Debug)]
+pub struct NaiveAgent;
+
+impl Agent for NaiveAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        let params = AgentParams::default();
+        let state = environment.get_state(player_id);
+        let action = if state.is_frozen() {
+            TagPlayerAction::Run {
+                stretch: RunStretch::zero(),
+            }
+        } else if environment.rules().mode == GameMode::Teams {
+            match environment.closest_player_where(player_id, |_, other| {
+                other.team != state.team && !other.is_frozen()
+            }) {
+                Some(closest_opponent) => chase(player_id, environment, &params, closest_opponent),
+                // every opponent is captured
+                None => TagPlayerAction::Run {
+                    stretch: RunStretch::zero(),
+                },
+            }
+        } else if state.is_it() {
+            chase_closest_target(player_id, environment, &params)
+        } else {
+            match environment.closest_player_where(player_id, |_, other| other.is_it()) {
+                Some((it_id, _)) => {
+                    let away = state.position - environment.get_state(it_id).position;
+                    let mut angle = away.angle_from_x_axis();
+                    if !angle.is_finite() {
+                        angle = Angle::radians(0.);
+                    }
+                    TagPlayerAction::Run {
+                        stretch: RunStretch::from_angle_and_length(
+                            angle,
+                            environment.rules().max_speed,
+                        ),
+                    }
+                }
+                None => TagPlayerAction::Run {
+                    stretch: RunStretch::zero(),
+                },
             }
-            let stretch = -RunStretch::from_angle_and_length(angle, MAX_SPEED);
-            let stretch = turn_at_edges(&environment.area(), *position, stretch);
-            TagPlayerAction::Run { stretch }
         };
         Ok(action)
     }
 }
 
-/// keeps the player running at full speed by turning them along the edge of the play area
-fn turn_at_edges(area: &PlayArea, from: Position, stretch: RunStretch) -> RunStretch {
+/// Chase the closest player that it can tag. If there is nobody it can tag, such as when the only other player has
+/// just tagged it and can't be tagged back, wait
+fn chase_closest_target(
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+    params: &AgentParams,
+) -> TagPlayerAction {
+    let target = match environment.rules().mode {
+        GameMode::Classic => {
+            let ignore = Option::<PlayerId>::from(environment.get_state(player_id).status)
+                .filter(|&tagger| environment.is_tag_back_banned(player_id, tagger));
+            environment.closest_player_except(player_id, ignore).ok()
+        }
+        GameMode::Freeze => {
+            environment.closest_player_where(player_id, |_, state| !state.is_frozen())
+        }
+        GameMode::Infection => {
+            environment.closest_player_where(player_id, |_, state| !state.is_it())
+        }
+        GameMode::Teams => unreachable!("nobody is it in team tag"),
+    };
+    match target {
+        Some(target) => chase(player_id, environment, params, target),
+        None => TagPlayerAction::Run {
+            stretch: RunStretch::zero(),
+        },
+    }
+}
+
+/// Run towards the target player, tagging them if they are within reach. While waiting out a tag cooldown stick to
+/// the target so they can be tagged as soon as it is over
+fn chase(
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+    params: &AgentParams,
+    (target, sq_distance): (PlayerId, PlayerDistance),
+) -> TagPlayerAction {
+    if sq_distance >= reach_squared(environment) {
+        run_towards(player_id, environment, params, target)
+    } else if environment.tag_cooldown(player_id) > 0 {
+        TagPlayerAction::Run {
+            stretch: environment.get_state(target).position
+                - environment.get_state(player_id).position,
+        }
+    } else {
+        TagPlayerAction::Tag { player_id: target }
+    }
+}
+
+/// Run to the closest frozen team mate and unfreeze them, as long as we will get there well before any of the threats
+fn rescue(
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+    params: &AgentParams,
+    is_threat: impl Fn(PlayerId, &TagPlayerVisibleState) -> bool,
+) -> Option<TagPlayerAction> {
+    let team = environment.get_state(player_id).team;
+    let (frozen_player, sq_distance) = environment
+        .closest_player_where(player_id, |_, state| {
+            state.is_frozen() && state.team == team
+        })
+        .filter(|(frozen_player, sq_distance)| {
+            environment
+                .closest_player_where(*frozen_player, &is_threat)
+                .is_none_or(|(_, threat_sq_distance)| {
+                    *sq_distance * params.rescue_caution.powi(2) < threat_sq_distance
+                })
+        })?;
+    Some(if sq_distance < reach_squared(environment) {
+        TagPlayerAction::Unfreeze {
+            player_id: frozen_player,
+        }
+    } else {
+        run_towards(player_id, environment, params, frozen_player)
+    })
+}
+
+/// Run away from all the chasers that are a threat, the closer ones matter more. If none are a threat stay put
+fn flee(
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+    params: &AgentParams,
+) -> TagPlayerAction {
+    let position = environment.get_state(player_id).position;
+    let mut threats = environment
+        .get_its()
+        .filter(|(it_id, _)| is_threat(player_id, environment, *it_id))
+        .peekable();
+    if threats.peek().is_none() {
+        return TagPlayerAction::Run {
+            stretch: RunStretch::zero(),
+        };
+    }
+    let vector = threats
+        .map(|(_, it)| position - it.position)
+        .filter(|away| away.square_length() > 0.)
+        .fold(RunStretch::zero(), |sum, away| {
+            sum + away / away.length().powf(params.flee_power)
+        });
+    let mut angle = vector.angle_from_x_axis();
+    if !angle.is_finite() {
+        angle = Angle::radians(0.);
+    }
+    let stretch = RunStretch::from_angle_and_length(
+        angle + Angle::degrees(params.flee_angle),
+        environment.rules().max_speed * params.speed,
+    );
+    let stretch = turn_at_edges(&environment.area(), params.edge_margin, position, stretch);
+    TagPlayerAction::Run { stretch }
+}
+
+/// Whether a chaser could tag the player soon. A newly tagged chaser waiting out a tag cooldown is no threat while
+/// they are too far away to get within reach before it is over
+fn is_threat(player_id: PlayerId, environment: &TagEnvironment, it_id: PlayerId) -> bool {
+    let cooldown = environment.tag_cooldown(it_id);
+    if cooldown == 0 {
+        return true;
+    }
+    let rules = environment.rules();
+    let frozen = environment
+        .get_state(it_id)
+        .status
+        .frozen_steps(rules, environment.step());
+    let closing_distance =
+        rules.tag_reach + rules.max_speed * cooldown.saturating_sub(frozen) as PlayerDistance;
+    let vector = environment.get_state(it_id).position - environment.get_state(player_id).position;
+    vector.square_length() <= closing_distance * closing_distance
+}
+
+/// Run at speed towards another player
+fn run_towards(
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+    params: &AgentParams,
+    target: PlayerId,
+) -> TagPlayerAction {
+    let vector = environment.get_state(target).position - environment.get_state(player_id).position;
+    TagPlayerAction::Run {
+        stretch: RunStretch::from_angle_and_length(
+            vector.angle_from_x_axis(),
+            environment.rules().max_speed * params.speed,
+        ),
+    }
+}
+
+/// Square of how far a player can reach, for comparing with square distances
+fn reach_squared(environment: &TagEnvironment) -> PlayerDistance {
+    environment.rules().tag_reach * environment.rules().tag_reach
+}
+
+/// keeps the player running at full speed by turning them along the edge of the play area, keeping a margin from it
+fn turn_at_edges(area: &PlayArea, margin: f32, from: Position, stretch: RunStretch) -> RunStretch {
     let target = from + stretch;
     let x_in_bounds = area.x_range().contains(&target.x);
     let y_in_bounds = area.y_range().contains(&target.y);
@@ -67,26 +381,28 @@ fn turn_at_edges(area: &PlayArea, from: Position, stretch: RunStretch) -> RunStr
         // headed into a corner
         (false, false) => {
             if stretch.x > stretch.y {
-                redirect_out_of_x_bounds(from, area, stretch)
+                redirect_out_of_x_bounds(from, area, margin, stretch)
             } else {
-                redirect_out_of_y_bounds(from, area, stretch)
+                redirect_out_of_y_bounds(from, area, margin, stretch)
             }
         }
         // headed off the top or bottom
-        (true, false) => redirect_out_of_y_bounds(from, area, stretch),
+        (true, false) => redirect_out_of_y_bounds(from, area, margin, stretch),
         // here, to avoid duplication, the same function is used with the axes swapped
-        (false, true) => redirect_out_of_x_bounds(from, area, stretch),
+        (false, true) => redirect_out_of_x_bounds(from, area, margin, stretch),
     }
 }
 
 fn redirect_out_of_x_bounds(
     from: euclid::Point2D<f32, euclid::UnknownUnit>,
     area: &euclid::Rect<f32, euclid::UnknownUnit>,
+    margin: f32,
     stretch: euclid::Vector2D<f32, euclid::UnknownUnit>,
 ) -> euclid::Vector2D<f32, euclid::UnknownUnit> {
     redirect_out_of_y_bounds(
         from.yx(),
-        &PlayArea::from_points(&[area.min().yx(), area.max().yx()]),
+        &PlayArea::from_points([area.min().yx(), area.max().yx()]),
+        margin,
         stretch.yx(),
     )
     .yx()
@@ -95,10 +411,10 @@ fn redirect_out_of_x_bounds(
 fn redirect_out_of_y_bounds(
     from: euclid::Point2D<f32, euclid::UnknownUnit>,
     area: &euclid::Rect<f32, euclid::UnknownUnit>,
+    margin: f32,
     stretch: euclid::Vector2D<f32, euclid::UnknownUnit>,
 ) -> euclid::Vector2D<f32, euclid::UnknownUnit> {
     // y broken
-    let margin = 0.1;
     let broken_y_bound = if (from + stretch).y > area.max_y() {
         area.max_y() - margin
     } else {
@@ -183,38 +499,61 @@ macro_rules! assert_valid_stretch {
     };
 }
 
+#[test]
+fn test_params_round_trip() {
+    let params = AgentParams {
+        speed: 0.75,
+        flee_angle: -12.5,
+        ..Default::default()
+    };
+    assert_eq!(params.to_string().parse(), Ok(params));
+    assert_eq!(
+        "flee_power = 3"
+            .parse::<AgentParams>()
+            .map(|p| p.flee_power),
+        Ok(3.)
+    );
+    assert!("flee_speed = 3".parse::<AgentParams>().is_err());
+}
+
 #[test]
 fn test_avoid_corners() {
-    let area = PlayArea::from_points(&[(0., 0.).into(), (10., 10.).into()]);
+    let area = PlayArea::from_points([Position::new(0., 0.), Position::new(10., 10.)]);
     let length = 3.;
+    let margin = AgentParams::default().edge_margin;
     let close_to_right_top: Position = (9.0, 8.0).into();
 
     let past_top = turn_at_edges(
         &area,
+        margin,
         close_to_right_top,
         RunStretch::from_angle_and_length(Angle::degrees(91.), length),
     );
     assert_valid_stretch!(close_to_right_top, past_top, *, length, area);
     let ok_1 = turn_at_edges(
         &area,
+        margin,
         close_to_right_top,
         RunStretch::from_angle_and_length(Angle::degrees(-91.), length),
     );
     assert_valid_stretch!(close_to_right_top, ok_1, -91., length, area);
     let past_right = turn_at_edges(
         &area,
+        margin,
         close_to_right_top,
         RunStretch::from_angle_and_length(Angle::degrees(1.), length),
     );
     assert_valid_stretch!(close_to_right_top, past_right, *, length, area);
     let ok_2 = turn_at_edges(
         &area,
+        margin,
         close_to_right_top,
         RunStretch::from_angle_and_length(Angle::degrees(-179.), length),
     );
     assert_valid_stretch!(close_to_right_top, ok_2, -179., length, area);
     let past_top_and_right = turn_at_edges(
         &area,
+        margin,
         close_to_right_top,
         RunStretch::from_angle_and_length(Angle::degrees(45.), length),
     );
@@ -224,32 +563,65 @@ fn test_avoid_corners() {
 
     let ok_3 = turn_at_edges(
         &area,
+        margin,
         close_to_left_bottom,
         RunStretch::from_angle_and_length(Angle::degrees(90.), length),
     );
     assert_valid_stretch!(close_to_left_bottom, ok_3, 90., length, area);
     let past_bottom = turn_at_edges(
         &area,
+        margin,
         close_to_left_bottom,
         RunStretch::from_angle_and_length(Angle::degrees(-90.), length),
     );
     assert_valid_stretch!(close_to_left_bottom, past_bottom, *, length, area);
     let ok_4 = turn_at_edges(
         &area,
+        margin,
         close_to_left_bottom,
         RunStretch::from_angle_and_length(Angle::degrees(0.), length),
     );
     assert_valid_stretch!(close_to_left_bottom, ok_4, 0., length, area);
     let past_left = turn_at_edges(
         &area,
+        margin,
         close_to_left_bottom,
         RunStretch::from_angle_and_length(Angle::degrees(180.), length),
     );
     assert_valid_stretch!(close_to_left_bottom, past_left, *, length, area);
     let past_bottom_and_left = turn_at_edges(
         &area,
+        margin,
         close_to_left_bottom,
         RunStretch::from_angle_and_length(Angle::degrees(180. + 45.), length),
     );
     assert_valid_stretch!(close_to_left_bottom, past_bottom_and_left, *, *, area);
 }
+
+#[test]
+fn test_nobody_left_to_capture() {
+    let environment = TagEnvironment::new(
+        PlayArea::from_points([Position::new(0., 0.), Position::new(10., 10.)]),
+        crate::rules::Rules::new(GameMode::Teams),
+        vec![
+            TagPlayerVisibleState {
+                position: (1., 1.).into(),
+                status: TagStatus::NotIt,
+                team: 0,
+            },
+            TagPlayerVisibleState {
+                position: (5., 5.).into(),
+                status: TagStatus::Frozen { tagged_by: 0 },
+                team: 1,
+            },
+        ],
+    );
+    let stand_still = TagPlayerAction::Run {
+        stretch: RunStretch::zero(),
+    };
+    assert_eq!(
+        TagPlayerAgent::default().act(0, &environment).unwrap(),
+        stand_still
+    );
+    assert_eq!(NaiveAgent.act(0, &environment).unwrap(), stand_still);
+}
@@ -0,0 +1,306 @@
+use std::{collections::VecDeque, f32::consts::TAU, fmt};
+
+use crate::{
+    environment::*,
+    rules::GameMode,
+    viewer::{fit, paint, write_coloured, Paint},
+};
+
+/// The dots of a Braille character are 2 wide and 4 high
+const DOTS_WIDE: usize = 2;
+const DOTS_HIGH: usize = 4;
+/// The bit of each dot in a Braille character, by row then column
+const DOT_BITS: [[u8; DOTS_WIDE]; DOTS_HIGH] =
+    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+/// Braille characters start from the one with no dots
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// What a dot is part of, a character takes its colour from the most important of its dots
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Layer {
+    Trail,
+    Reach,
+    Player,
+}
+
+/// Canvas drawing the field with Braille characters, each of which has 8 dots, so players stay apart until they are
+/// much closer than on the [`TagCanvas`](crate::viewer::TagCanvas)
+pub struct BrailleCanvas {
+    area: PlayArea,
+    /// Rows of characters
+    grid: Vec<Vec<BrailleCell>>,
+    /// Whether to colour the players and shade the jails with ANSI escape sequences
+    colour: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct BrailleCell {
+    dots: u8,
+    paint: Option<(Layer, Paint)>,
+    shaded: bool,
+}
+
+impl BrailleCanvas {
+    /// New canvas for drawing a particular playing field, as large as fits in the number of columns and rows while
+    /// keeping the shape of the field
+    pub fn new(area: PlayArea, columns: usize, rows: usize) -> Self {
+        let (width, height) = fit(area, columns, rows);
+        Self {
+            area,
+            grid: vec![vec![BrailleCell::default(); width]; height],
+            colour: false,
+        }
+    }
+
+    /// Draw in colour
+    pub fn coloured(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    /// How many dots there are across and down the canvas
+    fn dots(&self) -> (usize, usize) {
+        (self.grid[0].len() * DOTS_WIDE, self.grid.len() * DOTS_HIGH)
+    }
+
+    /// The dot a position is in, or none if it is outside the field
+    fn dot_at(&self, position: Position) -> Option<(usize, usize)> {
+        if position.x < self.area.min_x()
+            || position.y < self.area.min_y()
+            || position.x > self.area.max_x()
+            || position.y > self.area.max_y()
+        {
+            return None;
+        }
+        let (width, height) = self.dots();
+        let x = (position.x - self.area.min_x()) / self.area.width() * (width - 1) as f32;
+        let y = (position.y - self.area.min_y()) / self.area.height() * (height - 1) as f32;
+        Some((x.round() as usize, y.round() as usize))
+    }
+
+    /// Set a dot, offset by a number of dots from the one a position is in
+    fn plot(&mut self, position: Position, offset: (isize, isize), layer: Layer, paint: Paint) {
+        let Some((x, y)) = self.dot_at(position) else {
+            return;
+        };
+        let (width, height) = self.dots();
+        let (Some(x), Some(y)) = (
+            x.checked_add_signed(offset.0).filter(|x| *x < width),
+            y.checked_add_signed(offset.1).filter(|y| *y < height),
+        ) else {
+            return;
+        };
+        let cell = &mut self.grid[y / DOTS_HIGH][x / DOTS_WIDE];
+        cell.dots |= DOT_BITS[y % DOTS_HIGH][x % DOTS_WIDE];
+        if cell.paint.is_none_or(|(existing, _)| layer >= existing) {
+            cell.paint = Some((layer, paint));
+        }
+    }
+
+    /// Draw a player as a small shape of dots
+    fn player(&mut self, position: Position, shape: &[(isize, isize)], paint: Paint) {
+        for offset in shape {
+            self.plot(position, *offset, Layer::Player, paint);
+        }
+    }
+
+    /// Draw a line of dots from one position to another
+    fn line(&mut self, from: Position, to: Position, layer: Layer, paint: Paint) {
+        // a quarter of the width of a dot between each point on the line, so that there are no gaps
+        let spacing = self.area.width() / self.dots().0 as f32 / 4.;
+        let points = ((to - from).length() / spacing).ceil().max(1.) as usize;
+        for point in 0..=points {
+            self.plot(
+                from.lerp(to, point as f32 / points as f32),
+                (0, 0),
+                layer,
+                paint,
+            );
+        }
+    }
+
+    /// Draw a circle of dots around a position
+    fn circle(&mut self, centre: Position, radius: PlayerDistance, layer: Layer, paint: Paint) {
+        let points = (TAU * radius / (self.area.width() / self.dots().0 as f32) * 2.)
+            .ceil()
+            .max(8.) as usize;
+        for point in 0..points {
+            let angle = TAU * point as f32 / points as f32;
+            let offset = euclid::default::Vector2D::new(angle.cos(), angle.sin()) * radius;
+            self.plot(centre + offset, (0, 0), layer, paint);
+        }
+    }
+
+    /// Shade the background of part of the field
+    fn shade(&mut self, area: PlayArea) {
+        let (width, height) = self.dots();
+        let to_cell = |position: Position| {
+            let x = (position.x - self.area.min_x()) / self.area.width() * (width - 1) as f32;
+            let y = (position.y - self.area.min_y()) / self.area.height() * (height - 1) as f32;
+            (
+                (x.max(0.) as usize / DOTS_WIDE).min(self.grid[0].len() - 1),
+                (y.max(0.) as usize / DOTS_HIGH).min(self.grid.len() - 1),
+            )
+        };
+        let ((min_x, min_y), (max_x, max_y)) = (to_cell(area.min()), to_cell(area.max()));
+        for row in &mut self.grid[min_y..=max_y] {
+            for cell in &mut row[min_x..=max_x] {
+                cell.shaded = true;
+            }
+        }
+    }
+}
+
+/// A square of 4 dots
+const RUNNER: [(isize, isize); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+/// A cross of 5 dots
+const FROZEN: [(isize, isize); 5] = [(-1, -1), (1, -1), (0, 0), (-1, 1), (1, 1)];
+/// A ring of 8 dots
+const HUMAN: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Render the environment to the canvas, with a circle around each player who is it showing how far they can reach
+/// and a trail behind each player through where they were for the steps before
+pub fn render_braille(
+    environment: &TagEnvironment,
+    humans: &[PlayerId],
+    trails: &VecDeque<Vec<Option<Position>>>,
+    canvas: &mut BrailleCanvas,
+) {
+    if environment.rules().mode == GameMode::Teams {
+        for team in 0..environment.team_scores().len() {
+            canvas.shade(environment.jail(team));
+        }
+    }
+    for (player_id, player) in environment.players() {
+        let trail_paint = Paint {
+            highlight: false,
+            dim: true,
+            ..paint(environment, player_id)
+        };
+        let mut trail = trails
+            .iter()
+            .map(|positions| positions.get(player_id).copied().flatten())
+            // players who joined recently have no trail before they joined
+            .skip_while(Option::is_none)
+            .map_while(|position| position)
+            .chain([player.position]);
+        if let Some(mut from) = trail.next() {
+            for to in trail {
+                canvas.line(from, to, Layer::Trail, trail_paint);
+                from = to;
+            }
+        }
+    }
+    for (player_id, player) in environment.players() {
+        let paint = paint(environment, player_id);
+        if player.is_it() {
+            let reach = Paint {
+                highlight: false,
+                ..paint
+            };
+            canvas.circle(
+                player.position,
+                environment.rules().tag_reach,
+                Layer::Reach,
+                reach,
+            );
+        }
+        let shape: &[(isize, isize)] = if humans.contains(&player_id) {
+            &HUMAN
+        } else if player.is_frozen() {
+            &FROZEN
+        } else {
+            &RUNNER
+        };
+        canvas.player(player.position, shape, paint);
+    }
+}
+
+impl fmt::Display for BrailleCanvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "==================================")?;
+        for row in &self.grid {
+            for cell in row {
+                let mut dots = [0; 4];
+                let text = if cell.dots == 0 {
+                    " "
+                } else {
+                    char::from_u32(BRAILLE_BLANK + cell.dots as u32)
+                        .expect("Braille characters are valid")
+                        .encode_utf8(&mut dots)
+                };
+                if self.colour {
+                    write_coloured(f, text, cell.paint.map(|(_, paint)| paint), cell.shaded)?;
+                } else {
+                    f.write_str(text)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::rules::Rules;
+
+    fn is_set(canvas: &BrailleCanvas, position: Position) -> bool {
+        let (x, y) = canvas.dot_at(position).unwrap();
+        canvas.grid[y / DOTS_HIGH][x / DOTS_WIDE].dots & DOT_BITS[y % DOTS_HIGH][x % DOTS_WIDE] != 0
+    }
+
+    #[test]
+    fn draws_reach_and_trails() {
+        let area = Rect::from_points([Position::new(0., 0.), Position::new(10., 10.)]);
+        let environment = TagEnvironment::new(
+            area,
+            Rules::default(),
+            vec![
+                TagPlayerVisibleState {
+                    position: (5., 5.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (8., 2.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        let trails = VecDeque::from([
+            vec![None, None],
+            vec![Some((5., 9.).into()), Some((8., 8.).into())],
+        ]);
+        // 4 dots for each unit of the field
+        let mut canvas = BrailleCanvas::new(area, 20, 10);
+        render_braille(&environment, &[], &trails, &mut canvas);
+
+        let reach = environment.rules().tag_reach;
+        assert!(is_set(&canvas, (5. + reach, 5.).into()));
+        assert!(is_set(&canvas, (5., 5. - reach).into()));
+        // inside the circle, on the other side from the rest of the player
+        assert!(!is_set(&canvas, (5. - reach / 2., 5.).into()));
+        // each player has a trail from where they were
+        assert!(is_set(&canvas, (5., 7.).into()));
+        assert!(is_set(&canvas, (8., 5.).into()));
+        assert!(!is_set(&canvas, (2., 5.).into()));
+        assert!(canvas.to_string().lines().nth(1).unwrap().starts_with(' '));
+    }
+}
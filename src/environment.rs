@@ -1,13 +1,19 @@
-use std::error::Error;
+use std::{error::Error, fmt};
 
 use euclid::default::Vector2D;
 
+use crate::rules::{GameMode, Rules, TagBack, WinCondition};
+
 pub type PlayArea = euclid::default::Rect<PlayerDistance>;
 pub type Position = euclid::default::Point2D<f32>;
 pub type PlayerDistance = f32;
 pub type PlayerId = usize;
+pub type TeamId = usize;
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+/// Fraction of the play area's width and height taken up by each team's jail
+const JAIL_SIZE: f32 = 0.1;
+
 /// The state about each player which is visible through the environment to the other players
 #[derive(Debug)]
 pub struct TagPlayerVisibleState {
@@ -15,6 +21,8 @@ pub struct TagPlayerVisibleState {
     pub position: Position,
     /// Whether the player is it
     pub status: TagStatus,
+    /// The team the player plays for, only significant in team tag
+    pub team: TeamId,
 }
 
 /// Whether a player is it, and if they are who tagged them
@@ -22,21 +30,95 @@ pub struct TagPlayerVisibleState {
 pub enum TagStatus {
     /// The player is not it
     NotIt,
-    /// The player is it and was tagged by the indicated player
-    It { tagged_by: PlayerId },
+    /// The player is it and was tagged by the indicated player during the indicated step
+    It { tagged_by: PlayerId, tagged_at: u64 },
+    /// The player was tagged by the indicated player and can't move until another player unfreezes them
+    Frozen { tagged_by: PlayerId },
 }
 
 impl TagStatus {
     pub fn is_it(&self) -> bool {
         matches!(self, Self::It { .. })
     }
+
+    pub fn is_frozen(&self) -> bool {
+        matches!(self, Self::Frozen { .. })
+    }
+
+    /// How many more steps, including the current step, a newly tagged player has to wait before they can tag
+    pub fn tag_cooldown(&self, rules: &Rules, step: u64) -> u64 {
+        self.steps_left_of(rules.tag_cooldown, step)
+    }
+
+    /// How many more steps, including the current step, a newly tagged player has to stand still for
+    pub fn frozen_steps(&self, rules: &Rules, step: u64) -> u64 {
+        self.steps_left_of(rules.freeze_steps, step)
+    }
+
+    /// How many more steps, including the current step, are left of a period starting on the step after the player
+    /// was tagged
+    fn steps_left_of(&self, steps: u64, step: u64) -> u64 {
+        match self {
+            Self::It { tagged_at, .. } => steps_left_of(steps, *tagged_at, step),
+            _ => 0,
+        }
+    }
+}
+
+/// How many more steps, including the current step, are left of a period starting on the step after the one indicated
+fn steps_left_of(steps: u64, start: u64, step: u64) -> u64 {
+    let elapsed = step - start;
+    if (1..=steps).contains(&elapsed) {
+        steps - elapsed + 1
+    } else {
+        0
+    }
 }
 
 impl From<TagStatus> for Option<PlayerId> {
     fn from(status: TagStatus) -> Self {
         match status {
             TagStatus::NotIt => None,
-            TagStatus::It { tagged_by } => Some(tagged_by),
+            TagStatus::It { tagged_by, .. } | TagStatus::Frozen { tagged_by } => Some(tagged_by),
+        }
+    }
+}
+
+/// Something notable which happened while applying the actions of a step
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A player was tagged
+    Tagged { by: PlayerId, player: PlayerId },
+    /// A frozen or jailed player was freed
+    Unfrozen { by: PlayerId, player: PlayerId },
+    /// A team's score changed
+    TeamScored { team: TeamId, score: u32 },
+    /// A player's agent failed to choose a valid action
+    AgentFailed { player: PlayerId },
+    /// A player joined part way through the game
+    Joined { player: PlayerId },
+    /// A player was taken out of the game
+    Removed { player: PlayerId },
+    /// A player took over as it when the last player who was it left
+    Promoted { player: PlayerId },
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameEvent::Tagged { by, player } => write!(f, "Player {} tagged player {}", by, player),
+            GameEvent::Unfrozen { by, player } => {
+                write!(f, "Player {} freed player {}", by, player)
+            }
+            GameEvent::TeamScored { team, score } => {
+                write!(f, "Team {} has made {} captures", team, score)
+            }
+            GameEvent::AgentFailed { player } => write!(f, "Player {}'s agent failed", player),
+            GameEvent::Joined { player } => write!(f, "Player {} joined the game", player),
+            GameEvent::Removed { player } => {
+                write!(f, "Player {} was removed from the game", player)
+            }
+            GameEvent::Promoted { player } => write!(f, "Player {} is now it", player),
         }
     }
 }
@@ -45,6 +127,10 @@ impl TagPlayerVisibleState {
     pub fn is_it(&self) -> bool {
         self.status.is_it()
     }
+
+    pub fn is_frozen(&self) -> bool {
+        self.status.is_frozen()
+    }
 }
 
 /// Information about the state of the simulation that the player agents have access to
@@ -52,23 +138,35 @@ impl TagPlayerVisibleState {
 pub struct TagEnvironment {
     /// The game should be limited to this area
     area: PlayArea,
-    /// Visible state about all the players
+    /// The rules being played
+    rules: Rules,
+    /// Visible state about all the players, including those who have been removed
     player_state: Vec<TagPlayerVisibleState>,
-    /// Who is currently it
-    it_id: PlayerId,
+    /// Whether each player has been removed from the game, their id isn't reused
+    removed: Vec<bool>,
+    /// Captures made by each team
+    team_scores: Vec<u32>,
+    /// What happened during the last step
+    events: Vec<GameEvent>,
+    /// How many steps have been played
+    step: u64,
 }
 
 impl TagEnvironment {
-    pub fn new(area: PlayArea, player_state: Vec<TagPlayerVisibleState>) -> Self {
-        let it_id = player_state
-            .iter()
-            .enumerate()
-            .find_map(|(i, s)| s.is_it().then_some(i))
-            .expect("Expecting one player is it");
+    pub fn new(area: PlayArea, rules: Rules, player_state: Vec<TagPlayerVisibleState>) -> Self {
+        assert!(
+            rules.mode == GameMode::Teams || player_state.iter().any(TagPlayerVisibleState::is_it),
+            "Expecting at least one player is it"
+        );
+        let team_count = player_state.iter().map(|s| s.team + 1).max().unwrap_or(0);
         Self {
             area,
+            rules,
+            removed: vec![false; player_state.len()],
             player_state,
-            it_id,
+            team_scores: vec![0; team_count],
+            events: vec![],
+            step: 0,
         }
     }
 
@@ -77,27 +175,67 @@ impl TagEnvironment {
         &self.player_state[player_id]
     }
 
+    /// Iterate over all the players still in the game along with their ids
+    pub fn players(&self) -> impl Iterator<Item = (PlayerId, &TagPlayerVisibleState)> {
+        self.player_state
+            .iter()
+            .enumerate()
+            .filter(|(player_id, _)| !self.removed[*player_id])
+    }
+
+    /// Whether a player is still in the game
+    pub fn is_playing(&self, player_id: PlayerId) -> bool {
+        !self.removed[player_id]
+    }
+
+    /// Add a player who joins part way through the game, they get the next id
+    pub fn add_player(&mut self, state: TagPlayerVisibleState) -> PlayerId {
+        if self.team_scores.len() <= state.team {
+            self.team_scores.resize(state.team + 1, 0);
+        }
+        self.player_state.push(state);
+        self.removed.push(false);
+        self.player_state.len() - 1
+    }
+
+    /// Take a player out of the game, they keep their id but are ignored by everyone and their actions are ignored.
+    /// Nobody takes over from them if they were it
+    pub fn remove_player(&mut self, player_id: PlayerId) {
+        self.removed[player_id] = true;
+    }
+
+    /// Make a player it, as though they had tagged themselves
+    pub fn make_it(&mut self, player_id: PlayerId) {
+        self.player_state[player_id].status = TagStatus::It {
+            tagged_by: player_id,
+            tagged_at: self.step,
+        };
+    }
+
     /// Get the player closest to a specified player, optionally ignoring a player
     pub fn closest_player_except(
         &self,
         player_id: PlayerId,
         ignore: Option<PlayerId>,
     ) -> Result<(PlayerId, PlayerDistance)> {
+        self.closest_player_where(player_id, |i, _| Some(i) != ignore)
+            .ok_or_else(|| "Closest player with less than 2 players".into())
+    }
+
+    /// Get the player closest to a specified player out of the other players matching a predicate
+    pub fn closest_player_where(
+        &self,
+        player_id: PlayerId,
+        mut predicate: impl FnMut(PlayerId, &TagPlayerVisibleState) -> bool,
+    ) -> Option<(PlayerId, PlayerDistance)> {
         let mut closest_player = None;
         let my_position = self.get_state(player_id).position;
 
-        for (
-            i,
-            TagPlayerVisibleState {
-                position,
-                status: _,
-            },
-        ) in self.player_state.iter().enumerate()
-        {
-            if i == player_id || Some(i) == ignore {
+        for (i, state) in self.players() {
+            if i == player_id || !predicate(i, state) {
                 continue;
             }
-            let square_distance = (my_position - *position).square_length();
+            let square_distance = (my_position - state.position).square_length();
             if let Some((_, shortest_distance)) = closest_player {
                 if square_distance < shortest_distance {
                     closest_player = Some((i, square_distance));
@@ -106,7 +244,7 @@ impl TagEnvironment {
                 closest_player = Some((i, square_distance))
             }
         }
-        closest_player.ok_or_else(|| "Closest player with less than 2 players".into())
+        closest_player
     }
 
     /// Apply an action for each player to mutate the environment
@@ -115,17 +253,29 @@ impl TagEnvironment {
             self.player_state.len() == actions.len(),
             "Must apply one action for each player known to the environment"
         );
+        self.events.clear();
         for (idx, action) in actions.iter().enumerate() {
-            self.apply_action(idx, action)
+            if self.is_playing(idx) {
+                self.apply_action(idx, action)
+            }
         }
+        self.step += 1;
     }
 
     fn apply_action(&mut self, player_id: PlayerId, action: &TagPlayerAction) {
         match action {
             TagPlayerAction::Run { stretch } => {
                 assert!(stretch.is_finite());
+                if self.player_state[player_id].is_frozen() || self.is_stunned(player_id) {
+                    return;
+                }
+                let stretch = if stretch.length() > self.rules.max_speed {
+                    stretch.with_length(self.rules.max_speed)
+                } else {
+                    *stretch
+                };
                 let point2_d = &mut self.player_state[player_id].position;
-                *point2_d += *stretch;
+                *point2_d += stretch;
                 if point2_d.x < self.area.min_x() {
                     point2_d.x = self.area.min_x();
                 }
@@ -142,20 +292,267 @@ impl TagEnvironment {
             TagPlayerAction::Tag {
                 player_id: other_player_id,
             } => {
+                let other_player_id = *other_player_id;
                 // println!("{}: TAG {}", player_id, other_player_id);
+                if self.rules.mode == GameMode::Teams {
+                    assert!(
+                        self.player_state[player_id].team
+                            != self.player_state[other_player_id].team,
+                        "Player ({}) can't tag their own team mate",
+                        player_id
+                    );
+                    // either player may have been captured earlier this step
+                    if self.player_state[player_id].is_frozen()
+                        || self.player_state[other_player_id].is_frozen()
+                    {
+                        return;
+                    }
+                } else {
+                    assert!(
+                        self.player_state[player_id].is_it(),
+                        "Player ({}) can't tag if they're not it",
+                        player_id
+                    );
+                }
+                // the tag misses if the other player is out of reach or the rules don't allow tagging them yet
+                if !self.is_within_reach(player_id, other_player_id)
+                    || self.is_tag_back_banned(player_id, other_player_id)
+                    || self.tag_cooldown(player_id) > 0
+                {
+                    return;
+                }
+                match self.rules.mode {
+                    GameMode::Classic => {
+                        self.player_state[player_id].status = TagStatus::NotIt;
+                        self.player_state[other_player_id].status = TagStatus::It {
+                            tagged_by: player_id,
+                            tagged_at: self.step,
+                        };
+                    }
+                    GameMode::Freeze => {
+                        // another player may have been faster to unfreeze them
+                        if self.player_state[other_player_id].is_frozen() {
+                            return;
+                        }
+                        self.player_state[other_player_id].status = TagStatus::Frozen {
+                            tagged_by: player_id,
+                        };
+                    }
+                    GameMode::Infection => {
+                        // tagging a player who is already it doesn't change who infected them
+                        if self.player_state[other_player_id].is_it() {
+                            return;
+                        }
+                        self.player_state[other_player_id].status = TagStatus::It {
+                            tagged_by: player_id,
+                            tagged_at: self.step,
+                        };
+                    }
+                    GameMode::Teams => {
+                        let jail = self.jail(self.player_state[other_player_id].team);
+                        let captured = &mut self.player_state[other_player_id];
+                        captured.status = TagStatus::Frozen {
+                            tagged_by: player_id,
+                        };
+                        captured.position = jail.center();
+                    }
+                }
+                self.events.push(GameEvent::Tagged {
+                    by: player_id,
+                    player: other_player_id,
+                });
+                if self.rules.mode == GameMode::Teams {
+                    let team = self.player_state[player_id].team;
+                    self.team_scores[team] += 1;
+                    self.events.push(GameEvent::TeamScored {
+                        team,
+                        score: self.team_scores[team],
+                    });
+                }
+            }
+            TagPlayerAction::Unfreeze {
+                player_id: other_player_id,
+            } => {
+                let other_player_id = *other_player_id;
                 assert!(
-                    self.player_state[player_id].is_it(),
-                    "Player ({}) can't tag if they're not it",
+                    !self.player_state[player_id].is_it(),
+                    "Player ({}) can't unfreeze if they're it",
                     player_id
                 );
-                self.player_state[player_id].status = TagStatus::NotIt;
-                self.player_state[*other_player_id].status = TagStatus::It {
-                    tagged_by: player_id,
-                };
+                assert!(
+                    self.player_state[player_id].team == self.player_state[other_player_id].team,
+                    "Player ({}) can only unfreeze their own team mates",
+                    player_id
+                );
+                // the player may have been frozen earlier this step or not have reached the frozen player
+                if self.player_state[player_id].is_frozen()
+                    || !self.is_within_reach(player_id, other_player_id)
+                {
+                    return;
+                }
+                // another player may have already unfrozen them this step
+                if self.player_state[other_player_id].is_frozen() {
+                    self.player_state[other_player_id].status = TagStatus::NotIt;
+                    self.events.push(GameEvent::Unfrozen {
+                        by: player_id,
+                        player: other_player_id,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Check that the referee would accept an action from a player, for agents which can't be trusted to follow the
+    /// rules. Tags and unfreezes which miss are still valid
+    pub fn check_action(&self, player_id: PlayerId, action: &TagPlayerAction) -> Result<()> {
+        let state = &self.player_state[player_id];
+        match *action {
+            TagPlayerAction::Run { stretch } => {
+                if !stretch.is_finite() {
+                    return Err(format!("Player ({}) can't run by {:?}", player_id, stretch).into());
+                }
+            }
+            TagPlayerAction::Tag {
+                player_id: other_player_id,
+            } => {
+                let other = self.other_player(player_id, other_player_id)?;
+                if self.rules.mode == GameMode::Teams {
+                    if state.team == other.team {
+                        return Err(format!(
+                            "Player ({}) can't tag their own team mate",
+                            player_id
+                        )
+                        .into());
+                    }
+                } else if !state.is_it() {
+                    return Err(
+                        format!("Player ({}) can't tag if they're not it", player_id).into(),
+                    );
+                }
+            }
+            TagPlayerAction::Unfreeze {
+                player_id: other_player_id,
+            } => {
+                let other = self.other_player(player_id, other_player_id)?;
+                if state.is_it() {
+                    return Err(
+                        format!("Player ({}) can't unfreeze if they're it", player_id).into(),
+                    );
+                }
+                if state.team != other.team || !other.is_frozen() {
+                    return Err(format!(
+                        "Player ({}) can only unfreeze their own frozen team mates",
+                        player_id
+                    )
+                    .into());
+                }
             }
         }
+        Ok(())
+    }
+
+    fn other_player(
+        &self,
+        player_id: PlayerId,
+        other_player_id: PlayerId,
+    ) -> Result<&TagPlayerVisibleState> {
+        if other_player_id == player_id {
+            return Err(format!("Player ({}) can't target themselves", player_id).into());
+        }
+        self.player_state
+            .get(other_player_id)
+            .filter(|_| self.is_playing(other_player_id))
+            .ok_or_else(|| format!("There is no player ({})", other_player_id).into())
+    }
+
+    /// Whether one player is close enough to another to tag or unfreeze them
+    pub fn is_within_reach(&self, player_id: PlayerId, other_player_id: PlayerId) -> bool {
+        let vector =
+            self.player_state[other_player_id].position - self.player_state[player_id].position;
+        vector.square_length() <= self.rules.tag_reach * self.rules.tag_reach
+    }
+
+    /// Whether a player who is it is not yet allowed to tag back the other player, because they were just tagged by them
+    pub fn is_tag_back_banned(&self, player_id: PlayerId, other_player_id: PlayerId) -> bool {
+        match self.player_state[player_id].status {
+            TagStatus::It {
+                tagged_by,
+                tagged_at,
+            } if tagged_by == other_player_id => match self.rules.tag_back {
+                TagBack::Allowed => false,
+                TagBack::Never => true,
+                // the ban also covers the rest of the step they were tagged in
+                TagBack::AfterSteps(steps) => {
+                    steps > 0
+                        && (tagged_at == self.step
+                            || steps_left_of(steps, tagged_at, self.step) > 0)
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether a player is newly it and has to stand still for a while
+    pub fn is_stunned(&self, player_id: PlayerId) -> bool {
+        self.player_state[player_id]
+            .status
+            .frozen_steps(&self.rules, self.step)
+            > 0
     }
 
+    /// How many more steps, including this one, a newly tagged player has to wait before they can tag
+    pub fn tag_cooldown(&self, player_id: PlayerId) -> u64 {
+        self.player_state[player_id]
+            .status
+            .tag_cooldown(&self.rules, self.step)
+    }
+
+    /// Whether the game has been won
+    pub fn is_game_over(&self) -> bool {
+        if self.rules.win_condition == WinCondition::Never {
+            return false;
+        }
+        match self.rules.mode {
+            GameMode::Classic => false,
+            GameMode::Freeze => self
+                .players()
+                .all(|(_, state)| state.is_it() || state.is_frozen()),
+            GameMode::Infection => self.players().all(|(_, state)| state.is_it()),
+            GameMode::Teams => {
+                let mut free_teams = self
+                    .players()
+                    .filter(|(_, state)| !state.is_frozen())
+                    .map(|(_, state)| state.team);
+                let first = free_teams.next();
+                free_teams.all(|team| Some(team) == first)
+            }
+        }
+    }
+
+    /// The zone where captured players of a team are held in team tag, each team's jail is in a different corner
+    pub fn jail(&self, team: TeamId) -> PlayArea {
+        let size = self.area.size * JAIL_SIZE;
+        let (min, max) = (self.area.min(), self.area.max() - size);
+        let origin = match team % 4 {
+            0 => min,
+            1 => max,
+            2 => Position::new(max.x, min.y),
+            _ => Position::new(min.x, max.y),
+        };
+        PlayArea::new(origin, size)
+    }
+
+    /// Captures made by each team in team tag
+    pub fn team_scores(&self) -> &[u32] {
+        &self.team_scores
+    }
+
+    /// What happened while applying the last step's actions
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// The state of every player by their id, including those who have been removed
     pub fn player_state(&self) -> &[TagPlayerVisibleState] {
         &self.player_state
     }
@@ -164,18 +561,56 @@ impl TagEnvironment {
         self.area
     }
 
-    pub fn get_it(&self) -> &TagPlayerVisibleState {
-        &self.player_state[self.it_id]
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// How many steps have been played
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Iterate over all the players who are currently it
+    pub fn get_its(&self) -> impl Iterator<Item = (PlayerId, &TagPlayerVisibleState)> {
+        self.players().filter(|(_, state)| state.is_it())
     }
 }
 
 /// Action each player agent can choose to take after each step
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TagPlayerAction {
     /// Player can run a stretch
     Run { stretch: Vector2D<PlayerDistance> },
     /// Player can tag a player near to them
     Tag { player_id: PlayerId },
+    /// Player can unfreeze a frozen player near to them
+    Unfreeze { player_id: PlayerId },
+}
+
+/// An environment for testing agents on a field from the origin to the far corner with the default rules. Player 0
+/// is it and the others are running, all on team 0
+#[cfg(test)]
+pub fn test_environment(far_corner: (f32, f32), positions: &[(f32, f32)]) -> TagEnvironment {
+    TagEnvironment::new(
+        PlayArea::from_points([Position::new(0., 0.), far_corner.into()]),
+        Rules::default(),
+        positions
+            .iter()
+            .enumerate()
+            .map(|(player_id, position)| TagPlayerVisibleState {
+                position: (*position).into(),
+                status: if player_id == 0 {
+                    TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    }
+                } else {
+                    TagStatus::NotIt
+                },
+                team: 0,
+            })
+            .collect(),
+    )
 }
 
 #[cfg(test)]
@@ -184,22 +619,42 @@ mod test {
 
     use super::*;
 
+    /// Build an environment on a 100 x 100 field directly, so that the players don't need to make a valid game
+    fn environment(rules: Rules, player_state: Vec<TagPlayerVisibleState>) -> TagEnvironment {
+        TagEnvironment {
+            area: Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            rules,
+            removed: vec![false; player_state.len()],
+            player_state,
+            team_scores: vec![0],
+            events: vec![],
+            step: 0,
+        }
+    }
+
     #[test]
     fn apply_run() {
-        let mut e = TagEnvironment {
-            area: Rect::from_points(&[(0., 0.).into(), (100., 100.).into()]),
-            player_state: vec![
+        let mut e = environment(
+            Rules {
+                max_speed: 30.,
+                ..Rules::default()
+            },
+            vec![
                 TagPlayerVisibleState {
                     position: (0., 0.).into(),
                     status: TagStatus::NotIt,
+                    team: 0,
                 },
                 TagPlayerVisibleState {
                     position: (1., 1.).into(),
-                    status: TagStatus::It { tagged_by: 1 },
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
                 },
             ],
-            it_id: 1,
-        };
+        );
         assert_eq!(e.get_state(0).position, (0., 0.).into());
         assert_eq!(e.get_state(1).position, (1., 1.).into());
         e.apply_action(
@@ -222,14 +677,17 @@ mod test {
 
     #[test]
     fn apply_run_out_of_area() {
-        let mut e = TagEnvironment {
-            area: Rect::from_points(&[(0., 0.).into(), (100., 100.).into()]),
-            player_state: vec![TagPlayerVisibleState {
+        let mut e = environment(
+            Rules {
+                max_speed: 30.,
+                ..Rules::default()
+            },
+            vec![TagPlayerVisibleState {
                 position: (95., 0.).into(),
                 status: TagStatus::NotIt,
+                team: 0,
             }],
-            it_id: 1,
-        };
+        );
         assert_eq!(e.get_state(0).position, (95., 0.).into());
         e.apply_action(
             0,
@@ -242,20 +700,27 @@ mod test {
 
     #[test]
     fn apply_tag() {
-        let mut e = TagEnvironment {
-            area: Rect::from_points(&[(0., 0.).into(), (100., 100.).into()]),
-            player_state: vec![
+        let mut e = environment(
+            Rules {
+                tag_reach: 1.5,
+                ..Rules::new(GameMode::Classic)
+            },
+            vec![
                 TagPlayerVisibleState {
                     position: (0., 0.).into(),
                     status: TagStatus::NotIt,
+                    team: 0,
                 },
                 TagPlayerVisibleState {
                     position: (1., 1.).into(),
-                    status: TagStatus::It { tagged_by: 1 },
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
                 },
             ],
-            it_id: 1,
-        };
+        );
         assert!(!e.get_state(0).is_it());
         assert!(e.get_state(1).is_it());
         e.apply_action(1, &TagPlayerAction::Tag { player_id: 0 });
@@ -263,26 +728,299 @@ mod test {
         assert!(!e.get_state(1).is_it());
     }
 
+    #[test]
+    fn apply_freeze_tag_and_unfreeze() {
+        let mut e = environment(
+            Rules {
+                tag_reach: 1.5,
+                ..Rules::new(GameMode::Freeze)
+            },
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (1., 1.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (0., 1.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        e.apply_action(1, &TagPlayerAction::Tag { player_id: 0 });
+        assert!(e.get_state(0).is_frozen());
+        assert!(e.get_state(1).is_it());
+        assert!(!e.is_game_over());
+
+        e.apply_action(
+            0,
+            &TagPlayerAction::Run {
+                stretch: (10., 10.).into(),
+            },
+        );
+        assert_eq!(e.get_state(0).position, (0., 0.).into());
+
+        e.apply_action(2, &TagPlayerAction::Unfreeze { player_id: 0 });
+        assert!(!e.get_state(0).is_frozen());
+        assert!(!e.get_state(0).is_it());
+
+        e.apply_action(1, &TagPlayerAction::Tag { player_id: 0 });
+        e.apply_action(1, &TagPlayerAction::Tag { player_id: 2 });
+        assert!(e.is_game_over());
+    }
+
+    #[test]
+    fn apply_infection_tag() {
+        let mut e = environment(
+            Rules {
+                tag_reach: 1.5,
+                ..Rules::new(GameMode::Infection)
+            },
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (1., 1.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (0., 1.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        e.apply_action(1, &TagPlayerAction::Tag { player_id: 0 });
+        assert!(e.get_state(0).is_it());
+        assert!(e.get_state(1).is_it());
+        assert_eq!(e.get_its().count(), 2);
+        assert!(!e.is_game_over());
+        assert_eq!(
+            e.closest_player_where(2, |_, state| state.is_it())
+                .map(|(id, _)| id),
+            Some(0)
+        );
+
+        e.apply_action(0, &TagPlayerAction::Tag { player_id: 2 });
+        assert!(e.is_game_over());
+    }
+
+    #[test]
+    fn apply_team_capture_and_release() {
+        let mut e = TagEnvironment::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            Rules::new(GameMode::Teams),
+            vec![
+                TagPlayerVisibleState {
+                    position: (50., 50.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (50., 51.).into(),
+                    status: TagStatus::NotIt,
+                    team: 1,
+                },
+                TagPlayerVisibleState {
+                    position: (5., 5.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        e.apply_actions(&[
+            TagPlayerAction::Tag { player_id: 1 },
+            TagPlayerAction::Tag { player_id: 0 },
+            TagPlayerAction::Run {
+                stretch: (0., 0.).into(),
+            },
+        ]);
+        assert!(e.get_state(1).is_frozen());
+        assert!(!e.get_state(0).is_frozen());
+        assert!(e.jail(1).contains(e.get_state(1).position));
+        assert_eq!(e.team_scores(), &[1, 0]);
+        assert_eq!(
+            e.events(),
+            &[
+                GameEvent::Tagged { by: 0, player: 1 },
+                GameEvent::TeamScored { team: 0, score: 1 }
+            ]
+        );
+        assert!(e.is_game_over());
+    }
+
+    #[test]
+    fn referee_enforces_rules() {
+        let mut e = environment(
+            Rules {
+                tag_back: TagBack::AfterSteps(2),
+                freeze_steps: 1,
+                ..Rules::default()
+            },
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (5., 0.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+            ],
+        );
+        let stay = || TagPlayerAction::Run {
+            stretch: (0., 0.).into(),
+        };
+        let run = |x| TagPlayerAction::Run {
+            stretch: (x, 0.).into(),
+        };
+        // limited to max speed
+        e.apply_actions(&[stay(), run(-10.)]);
+        assert_eq!(e.get_state(1).position, (3., 0.).into());
+        // out of reach
+        e.apply_actions(&[stay(), TagPlayerAction::Tag { player_id: 0 }]);
+        assert!(e.get_state(1).is_it());
+        assert_eq!(e.events(), &[]);
+        e.apply_actions(&[stay(), run(-2.)]);
+        e.apply_actions(&[stay(), TagPlayerAction::Tag { player_id: 0 }]);
+        assert!(e.get_state(0).is_it());
+        assert_eq!(e.events(), &[GameEvent::Tagged { by: 1, player: 0 }]);
+        // newly tagged player has to stand still and can't tag back yet
+        assert!(e.is_stunned(0));
+        assert!(e.is_tag_back_banned(0, 1));
+        e.apply_actions(&[run(1.), stay()]);
+        assert_eq!(e.get_state(0).position, (0., 0.).into());
+        assert!(!e.is_stunned(0));
+        assert!(e.is_tag_back_banned(0, 1));
+        e.apply_actions(&[TagPlayerAction::Tag { player_id: 1 }, stay()]);
+        assert!(e.get_state(0).is_it());
+        assert!(!e.is_tag_back_banned(0, 1));
+        e.apply_actions(&[TagPlayerAction::Tag { player_id: 1 }, stay()]);
+        assert!(e.get_state(1).is_it());
+    }
+
+    #[test]
+    fn no_tag_back_in_the_same_step() {
+        let mut e = environment(
+            Rules {
+                tag_back: TagBack::AfterSteps(2),
+                ..Rules::default()
+            },
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (0.5, 0.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        // the second tag comes straight after the first, before the step is over
+        e.apply_actions(&[
+            TagPlayerAction::Tag { player_id: 1 },
+            TagPlayerAction::Tag { player_id: 0 },
+        ]);
+        assert!(e.get_state(1).is_it());
+        assert!(!e.get_state(0).is_it());
+        assert_eq!(e.events(), &[GameEvent::Tagged { by: 0, player: 1 }]);
+    }
+
+    #[test]
+    fn tag_cooldown_after_being_tagged() {
+        let mut e = environment(
+            Rules {
+                tag_back: TagBack::Allowed,
+                tag_cooldown: 2,
+                freeze_steps: 1,
+                ..Rules::default()
+            },
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (1., 0.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 1,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+            ],
+        );
+        let stay = || TagPlayerAction::Run {
+            stretch: (0., 0.).into(),
+        };
+        e.apply_actions(&[stay(), TagPlayerAction::Tag { player_id: 0 }]);
+        let status = e.get_state(0).status;
+        assert_eq!(status.tag_cooldown(e.rules(), e.step()), 2);
+        assert_eq!(status.frozen_steps(e.rules(), e.step()), 1);
+        e.apply_actions(&[TagPlayerAction::Tag { player_id: 1 }, stay()]);
+        assert!(e.get_state(0).is_it());
+        assert_eq!(e.tag_cooldown(0), 1);
+        assert!(!e.is_stunned(0));
+        e.apply_actions(&[TagPlayerAction::Tag { player_id: 1 }, stay()]);
+        assert!(e.get_state(0).is_it());
+        assert_eq!(e.tag_cooldown(0), 0);
+        e.apply_actions(&[TagPlayerAction::Tag { player_id: 1 }, stay()]);
+        assert!(e.get_state(1).is_it());
+    }
+
     #[test]
     fn test_closest_player() -> Result<()> {
-        let e = TagEnvironment {
-            area: Rect::from_points(&[(0., 0.).into(), (100., 100.).into()]),
-            player_state: vec![
+        let e = environment(
+            Rules::default(),
+            vec![
                 TagPlayerVisibleState {
                     position: (0., 0.).into(),
                     status: TagStatus::NotIt,
+                    team: 0,
                 },
                 TagPlayerVisibleState {
                     position: (5., 0.).into(),
                     status: TagStatus::NotIt,
+                    team: 0,
                 },
                 TagPlayerVisibleState {
                     position: (10., 10.).into(),
-                    status: TagStatus::It { tagged_by: 2 },
+                    status: TagStatus::It {
+                        tagged_by: 2,
+                        tagged_at: 0,
+                    },
+                    team: 0,
                 },
             ],
-            it_id: 2,
-        };
+        );
         assert_eq!(e.closest_player_except(0, None)?.0, 1);
         assert_eq!(e.closest_player_except(1, None)?.0, 0);
         assert_eq!(e.closest_player_except(2, None)?.0, 1);
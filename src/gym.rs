@@ -0,0 +1,335 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    observation::{observe, Action, Observation},
+    scenario::Scenario,
+    simulation::{choose_action, STAND_STILL},
+    strategy::Strategy,
+};
+
+/// A Gym style interface for training learned policies against the simulation. The learners are the players
+/// controlled through [`TagGym::step`], the rest are played by the opponent strategy
+#[derive(Debug)]
+pub struct TagGym {
+    scenario: Scenario,
+    reward: RewardShaping,
+    learners: Vec<PlayerId>,
+    opponent: Strategy,
+    /// Agents for the players who aren't learners
+    agents: Vec<Option<Box<dyn Agent>>>,
+    environment: TagEnvironment,
+    actions: Vec<TagPlayerAction>,
+}
+
+/// What happened after a step
+#[derive(Clone, Debug)]
+pub struct StepResult {
+    /// What each learner can see now
+    pub observations: Vec<Observation>,
+    /// The reward for each learner for the step
+    pub rewards: Vec<f32>,
+    /// Whether the episode is over, because the game was won or the step limit was reached
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// Extra information about a step which isn't part of the observations
+#[derive(Clone, Debug)]
+pub struct StepInfo {
+    /// How many steps have been played in the episode
+    pub step: u64,
+    pub events: Vec<GameEvent>,
+}
+
+impl TagGym {
+    /// The episode is ready to play with seed 0
+    pub fn new(
+        scenario: Scenario,
+        reward: RewardShaping,
+        learners: Vec<PlayerId>,
+        opponent: Strategy,
+    ) -> Self {
+        let environment =
+            TagEnvironment::new(scenario.area, scenario.rules, scenario.player_state(0));
+        let mut gym = Self {
+            scenario,
+            reward,
+            learners,
+            opponent,
+            agents: Vec::new(),
+            environment,
+            actions: Vec::new(),
+        };
+        gym.reset(0);
+        gym
+    }
+
+    /// Start a new episode with the players starting where the seed puts them, returns what each learner can see
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.environment = TagEnvironment::new(
+            self.scenario.area,
+            self.scenario.rules,
+            self.scenario.player_state(seed),
+        );
+        self.agents = (0..self.scenario.player_count)
+            .map(|player_id| (!self.learners.contains(&player_id)).then(|| self.opponent.agent()))
+            .collect();
+        self.observations()
+    }
+
+    /// Apply an action for each learner, along with the opponents' actions. An opponent whose agent fails or
+    /// chooses an action that isn't allowed stands still
+    pub fn step(&mut self, actions: &[Action]) -> Result<StepResult> {
+        assert_eq!(
+            actions.len(),
+            self.learners.len(),
+            "Must give one action for each learner"
+        );
+        self.actions.clear();
+        for (player_id, agent) in self.agents.iter_mut().enumerate() {
+            let action = match agent {
+                Some(agent) => choose_action(agent.as_mut(), player_id, &self.environment)
+                    .unwrap_or(STAND_STILL),
+                None => {
+                    let learner = self.learners.iter().position(|l| *l == player_id).unwrap();
+                    actions[learner].check()?;
+                    actions[learner].decode(&self.environment, player_id)
+                }
+            };
+            self.actions.push(action);
+        }
+        self.environment.apply_actions(&self.actions);
+
+        let rewards = self
+            .learners
+            .iter()
+            .map(|learner| self.reward.reward(&self.environment, *learner))
+            .collect();
+        Ok(StepResult {
+            observations: self.observations(),
+            rewards,
+            done: self.environment.is_game_over()
+                || self.environment.step() >= self.scenario.step_limit,
+            info: StepInfo {
+                step: self.environment.step(),
+                events: self.environment.events().to_vec(),
+            },
+        })
+    }
+
+    fn observations(&self) -> Vec<Observation> {
+        self.learners
+            .iter()
+            .map(|learner| observe(&self.environment, *learner))
+            .collect()
+    }
+}
+
+/// How much each thing that can happen to a player in a step is rewarded
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RewardShaping {
+    /// Each step spent neither it nor frozen
+    pub free: f32,
+    /// Each step spent it or frozen
+    pub caught: f32,
+    /// Tagging another player
+    pub tag: f32,
+    /// Being tagged
+    pub tagged: f32,
+    /// Unfreezing a team mate
+    pub unfreeze: f32,
+}
+
+impl RewardShaping {
+    /// The reward for a player for the step which has just been applied
+    fn reward(&self, environment: &TagEnvironment, player_id: PlayerId) -> f32 {
+        let state = environment.get_state(player_id);
+        let mut reward = if state.is_it() || state.is_frozen() {
+            self.caught
+        } else {
+            self.free
+        };
+        for event in environment.events() {
+            match *event {
+                GameEvent::Tagged { by, .. } if by == player_id => reward += self.tag,
+                GameEvent::Tagged { player, .. } if player == player_id => reward += self.tagged,
+                GameEvent::Unfrozen { by, .. } if by == player_id => reward += self.unfreeze,
+                _ => {}
+            }
+        }
+        reward
+    }
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        Self {
+            free: 0.01,
+            caught: -0.01,
+            tag: 1.,
+            tagged: -1.,
+            unfreeze: 0.5,
+        }
+    }
+}
+
+/// Comma separated `name=value` pairs, such as `tag=2,tagged=-2`, any rewards left out keep their default
+impl FromStr for RewardShaping {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut reward = Self::default();
+        for pair in s.split(',') {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected 'name=value' but found '{}'", pair))?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("Invalid reward for {} '{}'", name, value))?;
+            match name {
+                "free" => reward.free = value,
+                "caught" => reward.caught = value,
+                "tag" => reward.tag = value,
+                "tagged" => reward.tagged = value,
+                "unfreeze" => reward.unfreeze = value,
+                _ => return Err(format!("Unknown reward '{}'", name)),
+            }
+        }
+        Ok(reward)
+    }
+}
+
+impl fmt::Display for RewardShaping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "free={},caught={},tag={},tagged={},unfreeze={}",
+            self.free, self.caught, self.tag, self.tagged, self.unfreeze
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::{
+        observation::{DISCRETE_ACTIONS, OBSERVATION_SIZE},
+        rules::{GameMode, Rules},
+    };
+
+    #[test]
+    fn step_and_reward() {
+        let scenario = Scenario {
+            area: Rect::from_points([Position::new(0., 0.), Position::new(2., 2.)]),
+            rules: Rules::new(GameMode::Infection),
+            player_count: 2,
+            team_count: 2,
+            step_limit: 10,
+            end_conditions: Vec::new(),
+        };
+        let mut gym = TagGym::new(
+            scenario,
+            "tag=5".parse().unwrap(),
+            vec![0],
+            Strategy::Default,
+        );
+        let observations = gym.reset(3);
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].len(), OBSERVATION_SIZE);
+        // an action the policy can't choose is an error rather than a step
+        assert!(gym.step(&[Action::Discrete(DISCRETE_ACTIONS)]).is_err());
+        assert_eq!(gym.environment.step(), 0);
+
+        // it chases the other player until it can tag them, which wins the game
+        let mut total = 0.;
+        loop {
+            let observation = gym.observations()[0];
+            let action = if observation[5 + 6] == 1. {
+                Action::Discrete(9)
+            } else {
+                let (x, y) = (observation[5 + 1], observation[5 + 2]);
+                Action::Continuous([x * 100., y * 100., 0.])
+            };
+            let result = gym.step(&[action]).unwrap();
+            total += result.rewards[0];
+            if result.done {
+                assert_eq!(
+                    result.info.events,
+                    vec![GameEvent::Tagged { by: 0, player: 1 }]
+                );
+                assert!(result.info.step < 10);
+                break;
+            }
+        }
+        assert!((total - (5. - 0.01 * gym.environment.step() as f32)).abs() < 1e-5);
+    }
+
+    /// Tries to tag player 0 whether or not it's allowed to
+    #[derive(Debug)]
+    struct EagerTagger;
+
+    impl Agent for EagerTagger {
+        fn act(&mut self, _: PlayerId, _: &TagEnvironment) -> Result<TagPlayerAction> {
+            Ok(TagPlayerAction::Tag { player_id: 0 })
+        }
+    }
+
+    #[test]
+    fn invalid_opponent_stands_still() {
+        let scenario = Scenario {
+            area: Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            rules: Rules::new(GameMode::Infection),
+            player_count: 2,
+            team_count: 2,
+            step_limit: 10,
+            end_conditions: Vec::new(),
+        };
+        let mut gym = TagGym::new(
+            scenario,
+            RewardShaping::default(),
+            vec![0],
+            Strategy::Default,
+        );
+        gym.environment = TagEnvironment::new(
+            gym.scenario.area,
+            gym.scenario.rules,
+            vec![
+                TagPlayerVisibleState {
+                    position: (10., 10.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (90., 90.).into(),
+                    status: TagStatus::NotIt,
+                    team: 1,
+                },
+            ],
+        );
+        // a runner can't tag
+        gym.agents[1] = Some(Box::new(EagerTagger));
+        let result = gym.step(&[Action::Discrete(0)]).unwrap();
+        assert!(result.info.events.is_empty());
+        assert_eq!(
+            gym.environment.player_state()[1].position,
+            (90., 90.).into()
+        );
+    }
+
+    #[test]
+    fn parse_rewards() {
+        let reward: RewardShaping = "tag=2,tagged=-3".parse().unwrap();
+        assert_eq!(reward.tag, 2.);
+        assert_eq!(reward.tagged, -3.);
+        assert_eq!(reward.free, RewardShaping::default().free);
+        assert_eq!(reward.to_string().parse(), Ok(reward));
+        assert!("luck=1".parse::<RewardShaping>().is_err());
+    }
+}
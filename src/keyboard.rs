@@ -0,0 +1,310 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::{agent::Agent, environment::*};
+
+type RunStretch = euclid::default::Vector2D<f32>;
+
+/// Two direction keys pressed within this long of each other run diagonally
+const COMBINE: Duration = Duration::from_millis(150);
+
+/// The keys a human player uses
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyLayout {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    /// Tags the closest player in reach, or unfreezes the closest frozen team mate
+    pub tag: KeyCode,
+}
+
+pub const WASD: KeyLayout = KeyLayout {
+    up: KeyCode::Char('w'),
+    down: KeyCode::Char('s'),
+    left: KeyCode::Char('a'),
+    right: KeyCode::Char('d'),
+    tag: KeyCode::Char(' '),
+};
+
+pub const ARROWS: KeyLayout = KeyLayout {
+    up: KeyCode::Up,
+    down: KeyCode::Down,
+    left: KeyCode::Left,
+    right: KeyCode::Right,
+    tag: KeyCode::Enter,
+};
+
+/// What a human player has asked for with their keys
+#[derive(Debug)]
+struct Controls {
+    layouts: Vec<KeyLayout>,
+    /// Which way the player is running, each component is -1, 0 or 1
+    heading: (i8, i8),
+    /// When a direction key was last pressed
+    turned_at: Option<Instant>,
+    /// Whether the tag key has been pressed since the last step
+    tag: bool,
+}
+
+impl Controls {
+    /// Handle a key press, returning whether it was one of the player's keys
+    fn press(&mut self, key: KeyCode, now: Instant) -> bool {
+        let Some(layout) = self.layouts.iter().find(|layout| {
+            [
+                layout.up,
+                layout.down,
+                layout.left,
+                layout.right,
+                layout.tag,
+            ]
+            .contains(&key)
+        }) else {
+            return false;
+        };
+        let direction = match key {
+            key if key == layout.tag => {
+                self.tag = true;
+                return true;
+            }
+            key if key == layout.up => (0, -1),
+            key if key == layout.down => (0, 1),
+            key if key == layout.left => (-1, 0),
+            _ => (1, 0),
+        };
+        let combining = self
+            .turned_at
+            .is_some_and(|turned_at| now.duration_since(turned_at) < COMBINE);
+        // pressing the way back stops, pressing it again runs that way
+        self.heading = if self.heading == (-direction.0, -direction.1) {
+            (0, 0)
+        } else if combining {
+            (
+                if direction.0 != 0 {
+                    direction.0
+                } else {
+                    self.heading.0
+                },
+                if direction.1 != 0 {
+                    direction.1
+                } else {
+                    self.heading.1
+                },
+            )
+        } else {
+            direction
+        };
+        self.turned_at = Some(now);
+        true
+    }
+}
+
+/// A key pressed to control the viewer rather than a player
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// p pauses or resumes the game
+    Pause,
+    /// n plays one step while paused
+    Step,
+    /// + doubles the speed
+    Faster,
+    /// - halves the speed
+    Slower,
+    /// q, escape or ctrl-c
+    Quit,
+}
+
+/// Reads the keyboard on another thread for the viewer and the human players, the terminal has to be in raw mode
+#[derive(Debug)]
+pub struct Keyboard {
+    controls: Vec<Arc<Mutex<Controls>>>,
+    commands: Receiver<Command>,
+}
+
+impl Keyboard {
+    /// Start reading keys for a human player using each of the layouts
+    pub fn listen(layouts: Vec<Vec<KeyLayout>>) -> Self {
+        let (keyboard, sender) = Self::new(layouts);
+        let controls = keyboard.controls.clone();
+        thread::spawn(move || {
+            while let Ok(event) = event::read() {
+                if let Event::Key(key) = event {
+                    if let Some(command) = handle_key(&controls, key) {
+                        if sender.send(command).is_err() || command == Command::Quit {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        keyboard
+    }
+
+    fn new(layouts: Vec<Vec<KeyLayout>>) -> (Self, Sender<Command>) {
+        let (sender, commands) = mpsc::channel();
+        let keyboard = Self {
+            controls: layouts
+                .into_iter()
+                .map(|layouts| {
+                    Arc::new(Mutex::new(Controls {
+                        layouts,
+                        heading: (0, 0),
+                        turned_at: None,
+                        tag: false,
+                    }))
+                })
+                .collect(),
+            commands,
+        };
+        (keyboard, sender)
+    }
+
+    /// The agent for one of the human players
+    pub fn agent(&self, human: usize) -> KeyboardAgent {
+        KeyboardAgent {
+            controls: self.controls[human].clone(),
+        }
+    }
+
+    /// The viewer commands pressed since last asked
+    pub fn commands(&self) -> impl Iterator<Item = Command> + '_ {
+        self.commands.try_iter()
+    }
+}
+
+/// Pass a key to the players' controls, or return the viewer command it is for
+fn handle_key(controls: &[Arc<Mutex<Controls>>], key: KeyEvent) -> Option<Command> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(Command::Quit);
+    }
+    let now = Instant::now();
+    if controls
+        .iter()
+        .any(|controls| controls.lock().unwrap().press(key.code, now))
+    {
+        return None;
+    }
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => Some(Command::Quit),
+        KeyCode::Char('p') => Some(Command::Pause),
+        KeyCode::Char('n') => Some(Command::Step),
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(Command::Faster),
+        KeyCode::Char('-') => Some(Command::Slower),
+        _ => None,
+    }
+}
+
+/// Plays the way a human asks with their keys, they keep running the way they last chose
+#[derive(Debug)]
+pub struct KeyboardAgent {
+    controls: Arc<Mutex<Controls>>,
+}
+
+impl Agent for KeyboardAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        let mut controls = self.controls.lock().unwrap();
+        if std::mem::take(&mut controls.tag) {
+            // tag if they can, otherwise unfreeze
+            let actions: [fn(PlayerId) -> TagPlayerAction; 2] = [
+                |player_id| TagPlayerAction::Tag { player_id },
+                |player_id| TagPlayerAction::Unfreeze { player_id },
+            ];
+            for action in actions {
+                if let Some((other_id, _)) =
+                    environment.closest_player_where(player_id, |other_id, _| {
+                        environment.is_within_reach(player_id, other_id)
+                            && environment
+                                .check_action(player_id, &action(other_id))
+                                .is_ok()
+                    })
+                {
+                    return Ok(action(other_id));
+                }
+            }
+        }
+        let (x, y) = controls.heading;
+        let heading = RunStretch::new(x as f32, y as f32);
+        Ok(TagPlayerAction::Run {
+            stretch: if heading == RunStretch::zero() {
+                heading
+            } else {
+                heading.with_length(environment.rules().max_speed)
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn environment() -> TagEnvironment {
+        test_environment((10., 10.), &[(1., 1.), (1.5, 1.)])
+    }
+
+    fn press(keyboard: &Keyboard, code: KeyCode) -> Option<Command> {
+        handle_key(&keyboard.controls, KeyEvent::from(code))
+    }
+
+    #[test]
+    fn keys_steer_the_player() {
+        let (keyboard, _) = Keyboard::new(vec![vec![WASD, ARROWS]]);
+        let mut agent = keyboard.agent(0);
+        let environment = environment();
+        let run = |agent: &mut KeyboardAgent| match agent.act(1, &environment).unwrap() {
+            TagPlayerAction::Run { stretch } => stretch,
+            action => panic!("expected a run, got {:?}", action),
+        };
+        assert_eq!(run(&mut agent), RunStretch::zero());
+
+        press(&keyboard, KeyCode::Right);
+        assert_eq!(run(&mut agent), RunStretch::new(2., 0.));
+        // the second key comes straight after, so they run diagonally
+        press(&keyboard, KeyCode::Char('w'));
+        let stretch = run(&mut agent);
+        assert!(stretch.x > 0. && stretch.y < 0. && (stretch.length() - 2.).abs() < 1e-5);
+        keyboard.controls[0].lock().unwrap().turned_at = None;
+        press(&keyboard, KeyCode::Left);
+        assert_eq!(run(&mut agent), RunStretch::new(-2., 0.));
+        press(&keyboard, KeyCode::Right);
+        assert_eq!(run(&mut agent), RunStretch::zero());
+
+        // the runner can't tag, so they keep running
+        press(&keyboard, KeyCode::Char(' '));
+        assert!(matches!(
+            agent.act(1, &environment).unwrap(),
+            TagPlayerAction::Run { .. }
+        ));
+        press(&keyboard, KeyCode::Char(' '));
+        assert_eq!(
+            agent.act(0, &environment).unwrap(),
+            TagPlayerAction::Tag { player_id: 1 }
+        );
+
+        // out of reach the key does nothing
+        let environment = test_environment((10., 10.), &[(1., 1.), (5., 1.)]);
+        press(&keyboard, KeyCode::Char(' '));
+        assert!(matches!(
+            agent.act(0, &environment).unwrap(),
+            TagPlayerAction::Run { .. }
+        ));
+
+        assert_eq!(press(&keyboard, KeyCode::Char('p')), Some(Command::Pause));
+        assert_eq!(press(&keyboard, KeyCode::Char('q')), Some(Command::Quit));
+    }
+}
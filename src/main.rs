@@ -1,62 +1,619 @@
-use std::{env, iter, thread, time::Duration};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
-use agent::TagPlayerAgent;
-use environment::{PlayArea, TagPlayerVisibleState, TagStatus};
-use euclid::default::{Point2D, Rect};
-use rand::{Rng, SeedableRng};
-use simulation::Simulation;
+use environment::{GameEvent, PlayerId, Position, TagEnvironment};
+use euclid::default::Rect;
+use gym::{RewardShaping, TagGym};
+use keyboard::{Command, Keyboard, ARROWS, WASD};
+use qlearning::{random_baseline, QLearner};
+use rules::{GameMode, Rules, Succession, TagBack};
+use scenario::Scenario;
+use server::Server;
+use simulation::{EndCondition, FailurePolicy, Simulation, StepStatus};
+use strategy::{Strategy, DEFAULT_PROCESS_TIMEOUT};
+use sweep::{parse_values, Sweep};
+use tournament::{Pairing, Tournament};
+use trajectory::{ColumnarTrajectoryWriter, CsvTrajectoryWriter, TrajectoryWriter};
+use tuning::{Fitness, Tuner};
 
-use crate::viewer::{render_frame, TagCanvas};
+use crate::viewer::{Renderer, Screen, View};
 
 mod agent;
+mod braille;
 mod environment;
+mod gym;
+mod keyboard;
+mod mlp;
+mod observation;
+mod process;
+mod protocol;
+mod qlearning;
+mod rules;
+mod scenario;
+mod script;
+mod server;
 mod simulation;
+mod stats;
+mod strategy;
+mod sweep;
+mod tag_graph;
+mod tournament;
+mod trajectory;
+mod tuning;
 mod viewer;
+mod wasm;
+
+/// The range the speed of the game can be set or changed within from the keyboard, in steps per second
+const MIN_TICK_RATE: f64 = 0.5;
+const MAX_TICK_RATE: f64 = 1000.;
+/// How often the keyboard is checked while the game is paused
+const PAUSED_POLL: Duration = Duration::from_millis(50);
+/// The columns and rows the field fits in when the output isn't a terminal
+const DEFAULT_CANVAS_SIZE: (usize, usize) = (170, 50);
+
+const USAGE: &str =
+    "parameters are [tournament|sweep|tune|train] [player_count [step_limit]] [--mode classic|freeze|infection|teams] \
+    [--teams team_count] [--reach distance] [--speed distance] [--tag-back allowed|never|steps] \
+    [--tag-cooldown steps] [--freeze-steps steps] [--seconds time_limit] [--tags tag_limit] \
+    [--stale-steps steps] [--tag-graph file.dot|file.graphml] \
+    [--trajectory file.csv|file.bin] [--strategies name,...] [--seeds seed_count] [--swiss rounds] \
+    [--arena sizes] [--players counts] [--speeds distances] [--reaches distances] [--output file] \
+    [--opponent name] [--fitness free|tags] [--population size] [--generations count] \
+    [--episodes count] [--reward name=value,...] [--agent-timeout ms] \
+    [--on-failure skip|retry:count|remove|abort] [--arrivals step,...] [--it-leaves nearest|random] \
+    [--humans count] [--tick-rate steps_per_second] [--serve address] [--remote count] \
+    [--colour auto|always|never] [--canvas columnsxrows] \
+    [--render ascii|braille]";
 
 fn main() {
-    let player_count: usize = env::args()
-        .nth(1)
-        .map(|s| {
-            s.parse()
-                .expect("parameters are [player_count [step_limit]]")
-        })
+    let mut positional = Vec::new();
+    let mut mode = GameMode::Classic;
+    let mut team_count = 2;
+    let mut reach = None;
+    let mut speed = None;
+    let mut tag_back = None;
+    let mut tag_cooldown = None;
+    let mut freeze_steps = None;
+    let mut end_conditions = Vec::new();
+    let mut tag_graph_path = None;
+    let mut trajectory_path = None;
+    let mut strategies = None;
+    let mut seeds = 10;
+    let mut pairing = Pairing::RoundRobin;
+    let mut arena_sizes = vec![100.];
+    let mut player_counts = None;
+    let mut speeds = None;
+    let mut reaches = None;
+    let mut output = None;
+    let mut opponent = Strategy::Default;
+    let mut fitness = Fitness::FreeTime;
+    let mut population = 20;
+    let mut generations = 10;
+    let mut episodes = 200;
+    let mut reward = RewardShaping::default();
+    let mut agent_timeout = DEFAULT_PROCESS_TIMEOUT;
+    let mut on_failure = FailurePolicy::default();
+    let mut arrivals: Vec<u64> = Vec::new();
+    let mut succession = None;
+    let mut humans = 0;
+    let mut tick_rate = None;
+    let mut serve = None;
+    let mut remote = None;
+    let mut colour = None;
+    let mut canvas_size = None;
+    let mut renderer = Renderer::default();
+    let mut args = env::args().skip(1).peekable();
+    let command =
+        args.next_if(|arg| ["tournament", "sweep", "tune", "train"].contains(&arg.as_str()));
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => {
+                mode = match args.next().as_deref() {
+                    Some("classic") => GameMode::Classic,
+                    Some("freeze") => GameMode::Freeze,
+                    Some("infection") => GameMode::Infection,
+                    Some("teams") => GameMode::Teams,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--teams" => {
+                team_count = parse_arg(args.next());
+                assert!(team_count > 0, "{}", USAGE);
+            }
+            "--reach" => reach = Some(parse_arg(args.next())),
+            "--speed" => speed = Some(parse_arg(args.next())),
+            "--tag-back" => {
+                tag_back = Some(match args.next().as_deref() {
+                    Some("allowed") => TagBack::Allowed,
+                    Some("never") => TagBack::Never,
+                    steps => TagBack::AfterSteps(parse_arg(steps.map(str::to_owned))),
+                })
+            }
+            "--tag-cooldown" => tag_cooldown = Some(parse_arg(args.next())),
+            "--freeze-steps" => freeze_steps = Some(parse_arg(args.next())),
+            "--seconds" => end_conditions.push(EndCondition::WallClock(Duration::from_secs_f32(
+                parse_arg(args.next()),
+            ))),
+            "--tags" => end_conditions.push(EndCondition::TagCount(parse_arg(args.next()))),
+            "--stale-steps" => {
+                let stale_steps: u64 = parse_arg(args.next());
+                end_conditions.push(EndCondition::Custom(Arc::new(move |simulation| {
+                    let since = simulation.statistics().last_tag_at().unwrap_or(0);
+                    simulation.environment().step() - since >= stale_steps
+                })))
+            }
+            "--tag-graph" => tag_graph_path = Some(args.next().expect(USAGE)),
+            "--trajectory" => trajectory_path = Some(args.next().expect(USAGE)),
+            "--strategies" => {
+                let names = args.next().expect(USAGE);
+                strategies = Some(
+                    names
+                        .split(',')
+                        .map(|name| name.parse().unwrap_or_else(|error| panic!("{}", error)))
+                        .collect::<Vec<Strategy>>(),
+                )
+            }
+            "--seeds" => seeds = parse_arg(args.next()),
+            "--swiss" => {
+                pairing = Pairing::Swiss {
+                    rounds: parse_arg(args.next()),
+                }
+            }
+            "--arena" => arena_sizes = parse_sweep_values(args.next()),
+            "--players" => player_counts = Some(parse_sweep_values(args.next())),
+            "--speeds" => speeds = Some(parse_sweep_values(args.next())),
+            "--reaches" => reaches = Some(parse_sweep_values(args.next())),
+            "--output" => output = Some(args.next().expect(USAGE)),
+            "--opponent" => {
+                opponent = args
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|error| panic!("{}", error))
+            }
+            "--fitness" => {
+                fitness = match args.next().as_deref() {
+                    Some("free") => Fitness::FreeTime,
+                    Some("tags") => Fitness::TagsPerStep,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--population" => population = parse_arg(args.next()),
+            "--generations" => generations = parse_arg(args.next()),
+            "--episodes" => episodes = parse_arg(args.next()),
+            "--agent-timeout" => agent_timeout = Duration::from_millis(parse_arg(args.next())),
+            "--humans" => {
+                humans = parse_arg(args.next());
+                assert!(humans <= 2, "At most 2 players can share the keyboard");
+            }
+            "--serve" => serve = Some(args.next().expect(USAGE)),
+            "--remote" => remote = Some(parse_arg::<usize>(args.next())),
+            "--tick-rate" => {
+                let rate = parse_arg::<f64>(args.next());
+                assert!(
+                    (MIN_TICK_RATE..=MAX_TICK_RATE).contains(&rate),
+                    "The tick rate must be from {} to {} steps per second",
+                    MIN_TICK_RATE,
+                    MAX_TICK_RATE
+                );
+                tick_rate = Some(rate);
+            }
+            "--canvas" => {
+                let size = args.next().expect(USAGE);
+                let (columns, rows) = size.split_once('x').expect(USAGE);
+                canvas_size = Some((columns.parse().expect(USAGE), rows.parse().expect(USAGE)))
+            }
+            "--render" => {
+                renderer = match args.next().as_deref() {
+                    Some("ascii") => Renderer::Ascii,
+                    Some("braille") => Renderer::Braille,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--colour" => {
+                colour = match args.next().expect(USAGE).as_str() {
+                    "always" => Some(true),
+                    "never" => Some(false),
+                    "auto" => None,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--arrivals" => {
+                arrivals = args
+                    .next()
+                    .expect(USAGE)
+                    .split(',')
+                    .map(|step| step.parse().expect(USAGE))
+                    .collect()
+            }
+            "--it-leaves" => {
+                succession = match args.next().as_deref() {
+                    Some("nearest") => Some(Succession::Nearest),
+                    Some("random") => Some(Succession::Random),
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--on-failure" => {
+                on_failure = args
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|error| panic!("{}", error))
+            }
+            "--reward" => {
+                reward = args
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|error| panic!("{}", error))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    for strategy in strategies.iter_mut().flatten().chain([&mut opponent]) {
+        if let Strategy::Process { timeout, .. } = strategy {
+            *timeout = agent_timeout;
+        }
+    }
+    let player_count: usize = positional
+        .first()
+        .map(|s| s.parse().expect(USAGE))
         .unwrap_or(5);
-    let step_limit: usize = env::args()
-        .nth(2)
-        .map(|s| {
-            s.parse()
-                .expect("parameters are [player_count [step_limit]]")
-        })
+    let step_limit: u64 = positional
+        .get(1)
+        .map(|s| s.parse().expect(USAGE))
         .unwrap_or(100);
 
-    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
-    let area = Rect::from_points(&[(0., 0.).into(), (100., 100.).into()]);
-
-    let players = iter::once(TagPlayerVisibleState {
-        position: random_position(&mut rng, &area),
-        status: TagStatus::It { tagged_by: 0 },
-    })
-    .chain(iter::repeat_with(|| TagPlayerVisibleState {
-        position: random_position(&mut rng, &area),
-        status: TagStatus::NotIt,
-    }))
-    .map(|state| (TagPlayerAgent, state))
-    .take(player_count);
-
-    let mut simulation = Simulation::new(area, players);
-
-    let mut canvas;
-    for _step in 0..step_limit {
-        simulation.step();
+    let defaults = Rules::new(mode);
+    let rules = Rules {
+        tag_reach: reach.unwrap_or(defaults.tag_reach),
+        max_speed: speed.unwrap_or(defaults.max_speed),
+        tag_back: tag_back.unwrap_or(defaults.tag_back),
+        tag_cooldown: tag_cooldown.unwrap_or(defaults.tag_cooldown),
+        freeze_steps: freeze_steps.unwrap_or(defaults.freeze_steps),
+        succession: succession.unwrap_or(defaults.succession),
+        ..defaults
+    };
+
+    let scenario = Scenario {
+        area: Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+        rules,
+        player_count,
+        team_count,
+        step_limit,
+        end_conditions,
+    };
+
+    match command.as_deref() {
+        Some("tournament") => {
+            let tournament = Tournament {
+                scenario,
+                strategies: strategies.unwrap_or_else(|| vec![Strategy::Default, Strategy::Naive]),
+                seeds,
+                pairing,
+            };
+            print!("{}", tournament.run());
+            return;
+        }
+        Some("sweep") => {
+            let sweep = Sweep {
+                arena_sizes: arena_sizes.into_iter().map(|size| size as f32).collect(),
+                player_counts: player_counts.map_or(vec![player_count], |counts| {
+                    counts.into_iter().map(|count| count as usize).collect()
+                }),
+                speeds: speeds.map_or(vec![rules.max_speed], |speeds| {
+                    speeds.into_iter().map(|speed| speed as f32).collect()
+                }),
+                reaches: reaches.map_or(vec![rules.tag_reach], |reaches| {
+                    reaches.into_iter().map(|reach| reach as f32).collect()
+                }),
+                strategies: strategies.unwrap_or_else(|| vec![Strategy::Default]),
+                seeds,
+                base: scenario,
+            };
+            let output = output.unwrap_or_else(|| String::from("sweep.csv"));
+            let runs = sweep
+                .run(Path::new(&output))
+                .expect("Failed to write sweep results");
+            println!("Played {} runs, results are in {}", runs, output);
+            return;
+        }
+        Some("tune") => {
+            let tuner = Tuner {
+                scenario,
+                opponent,
+                fitness,
+                population,
+                generations,
+                seeds,
+            };
+            let output = output.unwrap_or_else(|| String::from("genome.txt"));
+            let fittest = tuner.run(|generation, individuals| {
+                let mean = individuals
+                    .iter()
+                    .map(|individual| individual.fitness)
+                    .sum::<f64>()
+                    / individuals.len() as f64;
+                println!(
+                    "Generation {}: best fitness {:.4}, mean {:.4}",
+                    generation + 1,
+                    individuals[0].fitness,
+                    mean
+                );
+                // save as we go so that the best genome so far is kept if tuning is interrupted
+                fs::write(&output, individuals[0].params.to_string())
+                    .expect("Failed to write genome");
+            });
+            print!("{}", fittest.params);
+            println!(
+                "Saved to {}, play it with --strategies genome:{}",
+                output, output
+            );
+            return;
+        }
+        Some("train") => {
+            // the learners play one side against the opponent
+            let learners = (0..player_count)
+                .filter(|player_id| scenario.side(*player_id) == 0)
+                .collect();
+            let mut gym = TagGym::new(scenario, reward, learners, opponent);
+            let baseline = random_baseline(&mut gym, episodes.min(20)).expect("Agent failed");
+            println!(
+                "Random actions: mean reward per learner {:.3}",
+                baseline
+                    .iter()
+                    .map(|episode| episode.mean_reward())
+                    .sum::<f32>()
+                    / baseline.len() as f32
+            );
+            let mut learner = QLearner::new(0.3, 0.1, 0.95);
+            let mut recent = Vec::new();
+            learner
+                .train(&mut gym, episodes, |number, episode| {
+                    recent.push(episode.clone());
+                    if recent.len() == 10 || number + 1 == episodes {
+                        let count = recent.len() as f32;
+                        println!(
+                            "Episode {}: mean reward per learner {:.3}, {:.1} steps, {:.1} tags",
+                            number + 1,
+                            recent
+                                .iter()
+                                .map(|episode| episode.mean_reward())
+                                .sum::<f32>()
+                                / count,
+                            recent
+                                .iter()
+                                .map(|episode| episode.steps as f32)
+                                .sum::<f32>()
+                                / count,
+                            recent
+                                .iter()
+                                .map(|episode| episode.tags as f32)
+                                .sum::<f32>()
+                                / count,
+                        );
+                        recent.clear();
+                    }
+                })
+                .expect("Agent failed");
+            println!("Learned values for {} states", learner.states());
+            return;
+        }
+        _ => {}
+    }
+
+    // clients can control the players after the humans, who are played by the strategies while nobody is connected.
+    // The address is printed before the game takes over the screen, and kept below the field while it does
+    let mut server =
+        serve.map(|address| Server::bind(&address).expect("Failed to start the server"));
+    let listening = server.as_ref().map(|server| {
+        format!(
+            "Listening on {}",
+            server.local_addr().expect("Server has an address")
+        )
+    });
+    if let Some(listening) = &listening {
+        println!("{}", listening);
+    }
+    // the game is shown full screen when it's watched in a terminal
+    let full_screen = humans > 0 || io::stdout().is_terminal();
+    // escape sequences would only get in the way of whatever reads the output otherwise
+    let colour = colour.unwrap_or_else(|| io::stdout().is_terminal());
+    let mut screen = full_screen.then(|| Screen::enter().expect("Failed to set up the terminal"));
+    // the human players come first, with one they can use either layout and with two they get one each
+    let keyboard = full_screen.then(|| {
+        Keyboard::listen(match humans {
+            0 => vec![],
+            1 => vec![vec![WASD, ARROWS]],
+            _ => vec![vec![WASD], vec![ARROWS]],
+        })
+    });
+    let human_ids: Vec<PlayerId> = (0..humans).collect();
+    let remote = remote.unwrap_or(player_count.saturating_sub(humans));
+    // players take turns to use each of the strategies
+    let strategies = strategies.unwrap_or_else(|| vec![Strategy::Default]);
+    let mut simulation = scenario
+        .simulation(0, |player_id| match (&keyboard, &mut server) {
+            (Some(keyboard), _) if player_id < humans => Box::new(keyboard.agent(player_id)),
+            (_, Some(server)) if player_id < humans + remote => {
+                Box::new(server.slot(player_id, strategies[player_id % strategies.len()].agent()))
+            }
+            _ => strategies[player_id % strategies.len()].agent(),
+        })
+        .on_failure(on_failure);
+    let mut messages = Vec::new();
+    if let Some(server) = &mut server {
+        messages = server.poll(simulation.environment());
+        server.broadcast(simulation.environment());
+    }
+
+    let mut trajectory = trajectory_path.map(|path| {
+        let out = BufWriter::new(File::create(&path).expect("Failed to create trajectory file"));
+        let writer: Box<dyn TrajectoryWriter> = if path.ends_with(".csv") {
+            Box::new(CsvTrajectoryWriter::new(out).expect("Failed to write trajectory"))
+        } else {
+            Box::new(ColumnarTrajectoryWriter::new(out).expect("Failed to write trajectory"))
+        };
+        writer
+    });
+
+    // people need time to react, otherwise the game runs at a watchable speed
+    let mut tick_rate = tick_rate.unwrap_or(if humans > 0 || server.is_some() {
+        10.
+    } else {
+        50.
+    });
+    let mut paused = false;
+    let mut next_tick = Instant::now();
+    let mut view = View::new(human_ids.clone(), colour, canvas_size, renderer);
+    'game: loop {
+        let mut single_step = false;
+        for command in keyboard.iter().flat_map(Keyboard::commands) {
+            match command {
+                Command::Pause => paused = !paused,
+                Command::Step => single_step = true,
+                Command::Faster => tick_rate = (tick_rate * 2.).min(MAX_TICK_RATE),
+                Command::Slower => tick_rate = (tick_rate / 2.).max(MIN_TICK_RATE),
+                Command::Quit => break 'game,
+            }
+        }
+        if paused && !single_step {
+            if let Some(screen) = &mut screen {
+                draw_full_screen(screen, &view, &messages, &simulation, paused, tick_rate);
+            }
+            thread::sleep(PAUSED_POLL);
+            continue;
+        }
+
+        view.record(simulation.environment());
+        let status = match simulation.step() {
+            Ok(status) => status,
+            Err(error) => {
+                drop(screen.take());
+                println!("{}", error);
+                break;
+            }
+        };
         let actions = simulation.actions();
-        canvas = TagCanvas::<170, 50>::new(simulation.environment().area());
-        render_frame(&simulation, actions, &mut canvas);
-        println!("{}", canvas);
-        thread::sleep(Duration::from_millis(20));
+        if let Some(trajectory) = &mut trajectory {
+            trajectory
+                .write_step(simulation.environment(), actions)
+                .expect("Failed to write trajectory");
+        }
+        // server messages from before the step go out with it
+        let mut step_messages: Vec<String> = human_ids
+            .iter()
+            .map(|human| {
+                format!(
+                    "Player {} (@): {}",
+                    human,
+                    human_status(simulation.environment(), *human)
+                )
+            })
+            .collect();
+        if screen.is_some() {
+            step_messages.extend(listening.clone());
+        }
+        step_messages.extend(simulation.events().iter().map(GameEvent::to_string));
+        step_messages.append(&mut messages);
+        messages = step_messages;
+        match &mut screen {
+            Some(screen) => {
+                draw_full_screen(screen, &view, &messages, &simulation, paused, tick_rate)
+            }
+            None => {
+                let (columns, rows) = DEFAULT_CANVAS_SIZE;
+                println!("{}", view.canvas(&simulation, columns, rows));
+                for message in &messages {
+                    println!("{}", message);
+                }
+            }
+        }
+        if let StepStatus::Ended(_) = status {
+            break;
+        }
+        // late players join in turn with the next strategy
+        let step = simulation.environment().step();
+        for _ in arrivals.iter().filter(|arrival| **arrival == step) {
+            let player_id = simulation.player_state().len();
+            simulation.add_player(
+                strategies[player_id % strategies.len()].agent(),
+                scenario.late_player_state(0, player_id),
+            );
+        }
+        if let Some(server) = &mut server {
+            messages = server.poll(simulation.environment());
+            server.broadcast(simulation.environment());
+        }
+        // after a pause or a slow step the game carries on from now rather than catching up
+        next_tick = (next_tick + Duration::from_secs_f64(1. / tick_rate)).max(Instant::now());
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+    drop(screen);
+    if let Some(server) = &mut server {
+        server.finish(&simulation.outcome());
+    }
+    if let Some(trajectory) = &mut trajectory {
+        trajectory.finish().expect("Failed to write trajectory");
+    }
+    print!("{}", simulation.outcome());
+
+    if let Some(path) = tag_graph_path {
+        let graph = simulation.tag_graph();
+        print!("{}", graph);
+        let mut out = BufWriter::new(File::create(&path).expect("Failed to create tag graph file"));
+        if path.ends_with(".graphml") {
+            graph.write_graphml(&mut out)
+        } else {
+            graph.write_dot(&mut out)
+        }
+        .expect("Failed to write tag graph");
     }
 }
 
-/// Select a random position within the play area
-fn random_position(rng: &mut rand::rngs::StdRng, area: &PlayArea) -> Point2D<f32> {
-    Point2D::new(rng.gen_range(area.x_range()), rng.gen_range(area.y_range()))
+/// Redraw the field with the messages below it and the side panel, sized for the terminal as it is now
+fn draw_full_screen(
+    screen: &mut Screen,
+    view: &View,
+    messages: &[String],
+    simulation: &Simulation,
+    paused: bool,
+    tick_rate: f64,
+) {
+    let size = screen.size().expect("Failed to read the terminal size");
+    screen
+        .draw(&view.full_screen(simulation, messages, paused, tick_rate, size))
+        .expect("Failed to draw the game");
+}
+
+/// What a human player needs to know about themselves
+fn human_status(environment: &TagEnvironment, player_id: PlayerId) -> &'static str {
+    let state = environment.get_state(player_id);
+    if !environment.is_playing(player_id) {
+        "you have left the game"
+    } else if state.is_it() {
+        "you're it, get close to someone and tag them"
+    } else if state.is_frozen() {
+        "you're frozen, wait for a team mate"
+    } else {
+        "run!"
+    }
+}
+
+/// Parse the value of a command line option
+fn parse_arg<T: FromStr>(value: Option<String>) -> T {
+    value.and_then(|s| s.parse().ok()).expect(USAGE)
+}
+
+/// Parse the values of a command line option to sweep over
+fn parse_sweep_values(value: Option<String>) -> Vec<f64> {
+    parse_values(&value.expect(USAGE)).unwrap_or_else(|error| panic!("{}", error))
 }
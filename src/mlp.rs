@@ -0,0 +1,281 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    observation::{observe, Action, DISCRETE_ACTIONS, OBSERVATION_SIZE},
+};
+
+/// A multilayer perceptron, a stack of fully connected layers
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    layers: Vec<Layer>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Layer {
+    inputs: usize,
+    /// A row of `inputs` weights for each output
+    weights: Vec<f32>,
+    biases: Vec<f32>,
+    activation: Activation,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Activation {
+    Linear,
+    Relu,
+    Tanh,
+}
+
+impl Mlp {
+    /// Run the network on the inputs
+    pub fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        let mut values = inputs.to_vec();
+        for layer in &self.layers {
+            values = layer
+                .weights
+                .chunks_exact(layer.inputs)
+                .zip(&layer.biases)
+                .map(|(row, bias)| {
+                    let sum = row.iter().zip(&values).map(|(w, v)| w * v).sum::<f32>() + bias;
+                    match layer.activation {
+                        Activation::Linear => sum,
+                        Activation::Relu => sum.max(0.),
+                        Activation::Tanh => sum.tanh(),
+                    }
+                })
+                .collect();
+        }
+        values
+    }
+
+    fn inputs(&self) -> usize {
+        self.layers[0].inputs
+    }
+
+    fn outputs(&self) -> usize {
+        self.layers[self.layers.len() - 1].biases.len()
+    }
+}
+
+/// Plays by a trained network, which is given the player's observation and outputs either 3 values for a continuous
+/// action or a value for each discrete action, of which the highest is taken
+#[derive(Debug)]
+pub struct MlpAgent {
+    pub network: Arc<Mlp>,
+}
+
+impl MlpAgent {
+    /// The network has to take an observation and output an action
+    pub fn new(network: Arc<Mlp>) -> std::result::Result<Self, String> {
+        if network.inputs() != OBSERVATION_SIZE {
+            return Err(format!(
+                "The network takes {} inputs but observations have {}",
+                network.inputs(),
+                OBSERVATION_SIZE
+            ));
+        }
+        if network.outputs() != 3 && network.outputs() != DISCRETE_ACTIONS {
+            return Err(format!(
+                "The network has {} outputs, it needs 3 for continuous actions or {} for discrete actions",
+                network.outputs(),
+                DISCRETE_ACTIONS
+            ));
+        }
+        Ok(Self { network })
+    }
+}
+
+impl Agent for MlpAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        let outputs = self.network.forward(&observe(environment, player_id));
+        let action = if let [x, y, interact] = outputs[..] {
+            Action::Continuous([x, y, interact])
+        } else {
+            let best = (0..outputs.len())
+                .max_by(|a, b| outputs[*a].total_cmp(&outputs[*b]))
+                .unwrap();
+            Action::Discrete(best)
+        };
+        Ok(action.decode(environment, player_id))
+    }
+}
+
+/// A line for each layer `layer <inputs> <outputs> <linear|relu|tanh>`, followed by a line of weights for each output
+/// and then a line of the biases. Lines starting with `#` are comments
+impl FromStr for Mlp {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let mut layers: Vec<Layer> = Vec::new();
+        while let Some(header) = lines.next() {
+            let (inputs, outputs, activation) =
+                match header.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["layer", inputs, outputs, activation] => (
+                        inputs
+                            .parse()
+                            .map_err(|_| format!("Invalid input count '{}'", inputs))?,
+                        outputs
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid output count '{}'", outputs))?,
+                        match activation {
+                            "linear" => Activation::Linear,
+                            "relu" => Activation::Relu,
+                            "tanh" => Activation::Tanh,
+                            _ => return Err(format!("Unknown activation '{}'", activation)),
+                        },
+                    ),
+                    _ => return Err(format!("Expected a layer header but found '{}'", header)),
+                };
+            if let Some(previous) = layers.last() {
+                if previous.biases.len() != inputs {
+                    return Err(format!(
+                        "Layer {} takes {} inputs but the layer before has {} outputs",
+                        layers.len() + 1,
+                        inputs,
+                        previous.biases.len()
+                    ));
+                }
+            }
+            let mut weights = Vec::with_capacity(inputs * outputs);
+            for _ in 0..outputs {
+                weights.extend(parse_row(lines.next(), inputs)?);
+            }
+            let biases = parse_row(lines.next(), outputs)?;
+            layers.push(Layer {
+                inputs,
+                weights,
+                biases,
+                activation,
+            });
+        }
+        if layers.is_empty() {
+            return Err(String::from("The network has no layers"));
+        }
+        Ok(Self { layers })
+    }
+}
+
+/// Parse a line of `count` numbers
+fn parse_row(line: Option<&str>, count: usize) -> std::result::Result<Vec<f32>, String> {
+    let line = line.ok_or("The network file ended part way through a layer")?;
+    let row = line
+        .split_whitespace()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid number '{}'", value))
+        })
+        .collect::<std::result::Result<Vec<f32>, _>>()?;
+    if row.len() != count {
+        return Err(format!("Expected {} numbers but found '{}'", count, line));
+    }
+    Ok(row)
+}
+
+impl fmt::Display for Mlp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for layer in &self.layers {
+            let activation = match layer.activation {
+                Activation::Linear => "linear",
+                Activation::Relu => "relu",
+                Activation::Tanh => "tanh",
+            };
+            writeln!(
+                f,
+                "layer {} {} {}",
+                layer.inputs,
+                layer.biases.len(),
+                activation
+            )?;
+            for row in layer
+                .weights
+                .chunks_exact(layer.inputs)
+                .chain([&layer.biases[..]])
+            {
+                let row: Vec<_> = row.iter().map(f32::to_string).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::{
+        rules::{GameMode, Rules},
+        scenario::Scenario,
+        simulation::StepStatus,
+    };
+
+    /// Runs towards the closest player and interacts with them when they are within reach
+    fn chaser() -> Mlp {
+        let mut weights = vec![vec![0.; OBSERVATION_SIZE]; 3];
+        weights[0][6] = 100.;
+        weights[1][7] = 100.;
+        weights[2][11] = 2.;
+        let rows: Vec<String> = weights
+            .iter()
+            .map(|row| row.iter().map(f32::to_string).collect::<Vec<_>>().join(" "))
+            .collect();
+        format!(
+            "# chaser\nlayer {} 3 linear\n{}\n0 0 -1\n",
+            OBSERVATION_SIZE,
+            rows.join("\n")
+        )
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn forward_pass() {
+        let network: Mlp = "layer 2 2 relu\n1 -1\n2 0\n0 -3\nlayer 2 1 tanh\n1 1\n0"
+            .parse()
+            .unwrap();
+        assert_eq!(network.forward(&[2., 1.]), vec![(1f32 + 1.).tanh()]);
+        assert_eq!(network.to_string().parse(), Ok(network));
+        assert!("layer 2 2 relu\n1 1\n1 1\n0 0\nlayer 3 1 linear\n1 1 1\n0"
+            .parse::<Mlp>()
+            .is_err());
+        assert!("layer 2 1 relu\n1\n0".parse::<Mlp>().is_err());
+    }
+
+    #[test]
+    fn network_tags() {
+        let network = Arc::new(chaser());
+        assert!(MlpAgent::new(Arc::new(
+            "layer 2 3 linear\n1 1\n1 1\n1 1\n0 0 0".parse().unwrap()
+        ))
+        .is_err());
+        let scenario = Scenario {
+            area: Rect::from_points([Position::new(0., 0.), Position::new(10., 10.)]),
+            rules: Rules::new(GameMode::Infection),
+            player_count: 2,
+            team_count: 2,
+            step_limit: 100,
+            end_conditions: Vec::new(),
+        };
+        let mut simulation = scenario.simulation(0, |player_id| {
+            if player_id == 0 {
+                Box::new(MlpAgent::new(network.clone()).unwrap())
+            } else {
+                Box::new(crate::agent::NaiveAgent)
+            }
+        });
+        while simulation.step().unwrap() == StepStatus::Running {}
+        assert_eq!(simulation.outcome().statistics.players()[0].tags_made, 1);
+    }
+}
@@ -0,0 +1,236 @@
+use euclid::Angle;
+
+use crate::{environment::*, rules::GameMode};
+
+type RunStretch = euclid::default::Vector2D<f32>;
+
+/// How many of the closest other players are included in an observation
+pub const OBSERVED_PLAYERS: usize = 3;
+/// Features describing the observing player
+const OWN_FEATURES: usize = 5;
+/// Features describing each of the other observed players
+const PLAYER_FEATURES: usize = 7;
+/// How many numbers there are in an observation
+pub const OBSERVATION_SIZE: usize = OWN_FEATURES + OBSERVED_PLAYERS * PLAYER_FEATURES;
+
+/// A fixed size encoding of what a player can see, for learned policies. All the values are between -1 and 1:
+///
+/// * the player's x and y as fractions of the play area
+/// * 1 if they are it, 1 if they are frozen and 1 if they have to wait before they can tag
+/// * then for each of the closest other players, closest first: 1 if there is a player, their x and y offset as
+///   fractions of the play area, 1 if they are it, 1 if they are frozen, 1 if they are a team mate and 1 if they are
+///   within reach. If there are fewer players these are all 0
+pub type Observation = [f32; OBSERVATION_SIZE];
+
+/// Encode what a player can see of the environment
+pub fn observe(environment: &TagEnvironment, player_id: PlayerId) -> Observation {
+    let area = environment.area();
+    let state = environment.get_state(player_id);
+    let mut observation = [0.; OBSERVATION_SIZE];
+    observation[..OWN_FEATURES].copy_from_slice(&[
+        (state.position.x - area.min_x()) / area.width(),
+        (state.position.y - area.min_y()) / area.height(),
+        flag(state.is_it()),
+        flag(state.is_frozen()),
+        flag(environment.tag_cooldown(player_id) > 0),
+    ]);
+
+    let mut others: Vec<_> = environment
+        .players()
+        .filter(|(other_id, _)| *other_id != player_id)
+        .map(|(other_id, other)| {
+            let sq_distance = (other.position - state.position).square_length();
+            (other_id, other, sq_distance)
+        })
+        .collect();
+    others.sort_by(|a, b| a.2.total_cmp(&b.2));
+    for (features, (other_id, other, _)) in observation[OWN_FEATURES..]
+        .chunks_exact_mut(PLAYER_FEATURES)
+        .zip(others)
+    {
+        let offset = other.position - state.position;
+        features.copy_from_slice(&[
+            1.,
+            offset.x / area.width(),
+            offset.y / area.height(),
+            flag(other.is_it()),
+            flag(other.is_frozen()),
+            flag(other.team == state.team),
+            flag(environment.is_within_reach(player_id, other_id)),
+        ]);
+    }
+    observation
+}
+
+fn flag(value: bool) -> f32 {
+    if value {
+        1.
+    } else {
+        0.
+    }
+}
+
+/// An action chosen by a learned policy, which is decoded into a [`TagPlayerAction`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    /// One of [`DISCRETE_ACTIONS`] choices: 0 stands still, 1 to 8 run at full speed in one of the 8 compass
+    /// directions starting east and turning anticlockwise, 9 tags the closest player that can be tagged and 10
+    /// unfreezes the closest frozen team mate
+    Discrete(usize),
+    /// Run by a stretch given as fractions of the maximum speed, tagging or unfreezing the closest player instead
+    /// when the third value is positive
+    Continuous([f32; 3]),
+}
+
+/// How many choices there are for a discrete action
+pub const DISCRETE_ACTIONS: usize = 11;
+
+impl Action {
+    /// Check the action is one of those a policy can choose
+    pub fn check(&self) -> Result<()> {
+        match self {
+            Action::Discrete(action) if *action >= DISCRETE_ACTIONS => Err(format!(
+                "Discrete actions are less than {}, not {}",
+                DISCRETE_ACTIONS, action
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Decode the action for a player. Tagging or unfreezing when nobody can be is standing still, so any action is
+    /// valid
+    pub fn decode(self, environment: &TagEnvironment, player_id: PlayerId) -> TagPlayerAction {
+        let max_speed = environment.rules().max_speed;
+        match self {
+            Action::Discrete(0) => stand_still(),
+            Action::Discrete(direction @ 1..=8) => TagPlayerAction::Run {
+                stretch: RunStretch::from_angle_and_length(
+                    Angle::degrees(45. * (direction - 1) as f32),
+                    max_speed,
+                ),
+            },
+            Action::Discrete(9) => tag_closest(environment, player_id),
+            Action::Discrete(10) => unfreeze_closest(environment, player_id),
+            Action::Discrete(action) => {
+                panic!(
+                    "Discrete actions are less than {}, not {}",
+                    DISCRETE_ACTIONS, action
+                )
+            }
+            Action::Continuous([_, _, interact]) if interact > 0. => {
+                match tag_closest(environment, player_id) {
+                    TagPlayerAction::Run { .. } => unfreeze_closest(environment, player_id),
+                    tag => tag,
+                }
+            }
+            Action::Continuous([x, y, _]) => {
+                let stretch = RunStretch::new(x, y) * max_speed;
+                TagPlayerAction::Run {
+                    stretch: if stretch.is_finite() {
+                        stretch
+                    } else {
+                        RunStretch::zero()
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn stand_still() -> TagPlayerAction {
+    TagPlayerAction::Run {
+        stretch: RunStretch::zero(),
+    }
+}
+
+/// Tag the closest player the referee would let this player tag, if they are within reach
+fn tag_closest(environment: &TagEnvironment, player_id: PlayerId) -> TagPlayerAction {
+    let state = environment.get_state(player_id);
+    let target = match environment.rules().mode {
+        GameMode::Teams => environment.closest_player_where(player_id, |_, other| {
+            other.team != state.team && !other.is_frozen()
+        }),
+        _ if !state.is_it() => None,
+        GameMode::Classic => environment.closest_player_where(player_id, |other_id, _| {
+            !environment.is_tag_back_banned(player_id, other_id)
+        }),
+        GameMode::Freeze => {
+            environment.closest_player_where(player_id, |_, other| !other.is_frozen())
+        }
+        GameMode::Infection => {
+            environment.closest_player_where(player_id, |_, other| !other.is_it())
+        }
+    };
+    match target {
+        Some((target, _)) if environment.is_within_reach(player_id, target) => {
+            TagPlayerAction::Tag { player_id: target }
+        }
+        _ => stand_still(),
+    }
+}
+
+/// Unfreeze the closest frozen team mate, if they are within reach
+fn unfreeze_closest(environment: &TagEnvironment, player_id: PlayerId) -> TagPlayerAction {
+    let state = environment.get_state(player_id);
+    if state.is_it() {
+        return stand_still();
+    }
+    let frozen_team_mate = environment.closest_player_where(player_id, |_, other| {
+        other.is_frozen() && other.team == state.team
+    });
+    match frozen_team_mate {
+        Some((frozen, _)) if environment.is_within_reach(player_id, frozen) => {
+            TagPlayerAction::Unfreeze { player_id: frozen }
+        }
+        _ => stand_still(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn environment() -> TagEnvironment {
+        test_environment((100., 50.), &[(50., 25.), (60., 25.), (50., 25.5)])
+    }
+
+    #[test]
+    fn encode_observation() {
+        let observation = observe(&environment(), 0);
+        assert_eq!(observation[..OWN_FEATURES], [0.5, 0.5, 1., 0., 0.]);
+        assert_eq!(
+            observation[OWN_FEATURES..OWN_FEATURES + 2 * PLAYER_FEATURES],
+            [1., 0., 0.01, 0., 0., 1., 1., 1., 0.1, 0., 0., 0., 1., 0.]
+        );
+        assert!(observation[OWN_FEATURES + 2 * PLAYER_FEATURES..]
+            .iter()
+            .all(|feature| *feature == 0.));
+    }
+
+    #[test]
+    fn decode_actions() {
+        let environment = environment();
+        assert_eq!(
+            Action::Discrete(9).decode(&environment, 0),
+            TagPlayerAction::Tag { player_id: 2 }
+        );
+        // runners can't tag
+        assert_eq!(Action::Discrete(9).decode(&environment, 1), stand_still());
+        assert_eq!(Action::Discrete(10).decode(&environment, 1), stand_still());
+        let TagPlayerAction::Run { stretch } = Action::Discrete(3).decode(&environment, 1) else {
+            panic!("expected a run")
+        };
+        assert!((stretch - RunStretch::new(0., 2.)).length() < 1e-5);
+        assert_eq!(
+            Action::Continuous([0.5, -0.5, 0.]).decode(&environment, 1),
+            TagPlayerAction::Run {
+                stretch: RunStretch::new(1., -1.)
+            }
+        );
+        assert_eq!(
+            Action::Continuous([0.5, -0.5, 1.]).decode(&environment, 0),
+            TagPlayerAction::Tag { player_id: 2 }
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    protocol::{StepRequest, StepResponse},
+};
+
+/// Extra time allowed for the first step, while the process starts up
+const STARTUP_GRACE: Duration = Duration::from_secs(2);
+/// How long a process has to exit by itself once its input is closed, before it is killed
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Plays by asking a child process, which is sent a [`StepRequest`] as a line of JSON on its standard input each step
+/// and has to answer with a [`StepResponse`] line on its standard output within the timeout. When it doesn't the
+/// agent fails, so the simulation skips the player's turn
+#[derive(Debug)]
+pub struct ProcessAgent {
+    child: Child,
+    /// Closed once the process can't be written to
+    stdin: Option<ChildStdin>,
+    /// Lines the process writes, read on another thread so that waiting for them can time out
+    lines: Receiver<io::Result<String>>,
+    /// The last line the process wrote to its standard error, which is kept off the terminal and explains why it
+    /// stopped
+    last_error: Arc<Mutex<Option<String>>>,
+    /// Reads the standard error until the process closes it
+    error_reader: Option<JoinHandle<()>>,
+    timeout: Duration,
+    /// Whether a step has been requested yet
+    started: bool,
+}
+
+impl ProcessAgent {
+    /// Start the command, which is split on whitespace into the program and its arguments
+    pub fn spawn(command: &str, timeout: Duration) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The command is empty"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("Standard output is piped");
+        let stderr = child.stderr.take().expect("Standard error is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let last_error = Arc::new(Mutex::new(None));
+        let error_reader = thread::spawn({
+            let last_error = last_error.clone();
+            move || {
+                for line in BufReader::new(stderr).lines().map_while(io::Result::ok) {
+                    *last_error.lock().unwrap() = Some(line);
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            last_error,
+            error_reader: Some(error_reader),
+            timeout,
+            started: false,
+        })
+    }
+
+    fn request(&mut self, request: &StepRequest) -> Result<()> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(self.stopped("The process has stopped".into()));
+        };
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        if let Err(error) = stdin.write_all(line.as_bytes()).and_then(|_| stdin.flush()) {
+            return Err(self.stopped(format!("Failed to write to the process: {}", error)));
+        }
+        Ok(())
+    }
+
+    /// Why the process can't play any more, with the last error it wrote
+    fn stopped(&mut self, reason: String) -> Box<dyn std::error::Error> {
+        self.stdin = None;
+        // give the process a moment to finish writing its standard error after closing its output
+        if let Some(error_reader) = self.error_reader.take() {
+            let deadline = Instant::now() + SHUTDOWN_GRACE;
+            while !error_reader.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        match &*self.last_error.lock().unwrap() {
+            Some(last_error) => format!("{}, it last said: {}", reason, last_error).into(),
+            None => reason.into(),
+        }
+    }
+
+    /// Wait for the answer for a step, skipping late answers for earlier steps
+    fn response(&mut self, step: u64) -> Result<TagPlayerAction> {
+        let mut timeout = self.timeout;
+        if !self.started {
+            self.started = true;
+            timeout += STARTUP_GRACE;
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line = match self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!("No answer within {:?}", timeout).into())
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(self.stopped("The process has stopped".into()))
+                }
+            };
+            let response: StepResponse = serde_json::from_str(&line)
+                .map_err(|error| format!("Invalid answer '{}': {}", line, error))?;
+            if response.step == step {
+                return Ok(response.action.into());
+            }
+        }
+    }
+}
+
+impl Agent for ProcessAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        let request = StepRequest::new(environment, player_id);
+        self.request(&request)?;
+        self.response(request.step)
+    }
+}
+
+impl Drop for ProcessAgent {
+    /// Close the process's input so it can exit cleanly, and kill it if it doesn't
+    fn drop(&mut self) {
+        self.stdin = None;
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(Some(_)) | Err(_) => return,
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Vector2D;
+
+    use super::*;
+
+    fn environment() -> TagEnvironment {
+        test_environment((10., 10.), &[(1., 1.), (5., 5.)])
+    }
+
+    /// A process which answers each request for a step by running east
+    const RUN_EAST: &str =
+        r#"sed -u s/^{"step":\([0-9]*\).*/{"step":\1,"action":{"run":{"dx":1,"dy":0}}}/"#;
+
+    #[test]
+    fn answers_over_json_lines() {
+        let mut agent = ProcessAgent::spawn(RUN_EAST, Duration::from_secs(1)).unwrap();
+        let environment = environment();
+        for _ in 0..3 {
+            assert_eq!(
+                agent.act(0, &environment).unwrap(),
+                TagPlayerAction::Run {
+                    stretch: Vector2D::new(1., 0.)
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn fails_without_an_answer() {
+        let started = Instant::now();
+        let mut agent = ProcessAgent::spawn("sleep 10", Duration::from_millis(10)).unwrap();
+        let environment = environment();
+        assert!(agent.act(0, &environment).is_err());
+        assert!(agent.act(0, &environment).is_err());
+        drop(agent);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut agent = ProcessAgent::spawn("true", Duration::from_millis(10)).unwrap();
+        assert!(agent.act(0, &environment).is_err());
+    }
+
+    #[test]
+    fn reports_what_the_process_said_when_it_stops() {
+        let mut agent =
+            ProcessAgent::spawn("ls /no-such-agent", Duration::from_millis(100)).unwrap();
+        let error = agent.act(0, &environment()).unwrap_err().to_string();
+        assert!(error.contains("/no-such-agent"), "{}", error);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    environment::*,
+    observation::{observe, Observation},
+    rules::GameMode,
+};
+
+type RunStretch = euclid::default::Vector2D<f32>;
+
+/// The version of the protocol, which changes whenever a message changes in a way old agents won't understand
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// What an external agent is sent each step, as one line of JSON
+#[derive(Clone, Debug, Serialize)]
+pub struct StepRequest {
+    /// The step being decided, which the response has to repeat
+    pub step: u64,
+    /// The [`PROTOCOL_VERSION`]
+    pub version: u32,
+    /// The player the agent is choosing an action for
+    pub player_id: PlayerId,
+    /// The fixed size observation learned policies use
+    pub observation: Observation,
+    pub area: AreaMessage,
+    pub rules: RulesMessage,
+    /// Every player, indexed by their id
+    pub players: Vec<PlayerMessage>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AreaMessage {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RulesMessage {
+    pub mode: GameMode,
+    pub tag_reach: f32,
+    pub max_speed: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerMessage {
+    pub x: f32,
+    pub y: f32,
+    pub status: StatusMessage,
+    pub team: TeamId,
+    /// Whether the player is still in the game
+    pub playing: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusMessage {
+    NotIt,
+    It,
+    Frozen,
+}
+
+/// What an external agent answers with, as one line of JSON
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StepResponse {
+    /// The step the action is for, answers for earlier steps arrived too late and are ignored
+    pub step: u64,
+    pub action: ActionMessage,
+}
+
+/// An action, such as `{"run": {"dx": 1, "dy": -0.5}}`, `{"tag": 3}` or `{"unfreeze": 2}`
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionMessage {
+    Run { dx: f32, dy: f32 },
+    Tag(PlayerId),
+    Unfreeze(PlayerId),
+}
+
+/// What a network client is sent by the server, as one line of JSON such as `{"welcome": {..}}`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent once a client has connected, with the player it controls
+    Welcome { version: u32, player_id: PlayerId },
+    /// Sent when every player is already controlled by a client, then the connection is closed
+    Full { version: u32 },
+    /// The state of the game before each step, which the client answers with a [`StepResponse`]
+    Step(StepRequest),
+    /// The client's player has been removed from the game, then the connection is closed
+    Removed { player_id: PlayerId },
+    /// The game has ended, with each player's score
+    GameOver { steps: u64, scores: Vec<u64> },
+}
+
+impl StepRequest {
+    /// What a player can see of the environment
+    pub fn new(environment: &TagEnvironment, player_id: PlayerId) -> Self {
+        let area = environment.area();
+        let rules = environment.rules();
+        Self {
+            step: environment.step(),
+            version: PROTOCOL_VERSION,
+            player_id,
+            observation: observe(environment, player_id),
+            area: AreaMessage {
+                x: area.min_x(),
+                y: area.min_y(),
+                width: area.width(),
+                height: area.height(),
+            },
+            rules: RulesMessage {
+                mode: rules.mode,
+                tag_reach: rules.tag_reach,
+                max_speed: rules.max_speed,
+            },
+            players: environment
+                .player_state()
+                .iter()
+                .enumerate()
+                .map(|(id, state)| PlayerMessage {
+                    x: state.position.x,
+                    y: state.position.y,
+                    status: match state.status {
+                        TagStatus::NotIt => StatusMessage::NotIt,
+                        TagStatus::It { .. } => StatusMessage::It,
+                        TagStatus::Frozen { .. } => StatusMessage::Frozen,
+                    },
+                    team: state.team,
+                    playing: environment.is_playing(id),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<ActionMessage> for TagPlayerAction {
+    fn from(action: ActionMessage) -> Self {
+        match action {
+            ActionMessage::Run { dx, dy } => TagPlayerAction::Run {
+                stretch: RunStretch::new(dx, dy),
+            },
+            ActionMessage::Tag(player_id) => TagPlayerAction::Tag { player_id },
+            ActionMessage::Unfreeze(player_id) => TagPlayerAction::Unfreeze { player_id },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_actions() {
+        let response: StepResponse =
+            serde_json::from_str(r#"{"step": 4, "action": {"run": {"dx": 1, "dy": -0.5}}}"#)
+                .unwrap();
+        assert_eq!(response.step, 4);
+        assert_eq!(
+            TagPlayerAction::from(response.action),
+            TagPlayerAction::Run {
+                stretch: RunStretch::new(1., -0.5)
+            }
+        );
+        let response: StepResponse =
+            serde_json::from_str(r#"{"step": 5, "action": {"tag": 3}}"#).unwrap();
+        assert_eq!(response.action, ActionMessage::Tag(3));
+        assert!(serde_json::from_str::<StepResponse>(r#"{"step": 5, "action": "jump"}"#).is_err());
+    }
+}
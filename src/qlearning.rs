@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    environment::{GameEvent, Result},
+    gym::{StepResult, TagGym},
+    observation::{Action, Observation, DISCRETE_ACTIONS},
+};
+
+/// Index of the features of the closest other player in an observation
+const CLOSEST: usize = 5;
+/// Distances as a fraction of the play area's width which are close, beyond reach
+const CLOSE: f32 = 0.1;
+
+/// Tabular Q-learning over a coarse state built from an observation: the player's own status, which way and how far
+/// the closest other player is and whether they are it, and which edges of the play area the player is near. All the
+/// learners share the same table
+#[derive(Debug)]
+pub struct QLearner {
+    table: HashMap<usize, [f32; DISCRETE_ACTIONS]>,
+    /// The chance of a random action while training, which decays to a tenth of this over the episodes
+    pub epsilon: f64,
+    /// Learning rate
+    pub alpha: f32,
+    /// Discount of future rewards
+    pub gamma: f32,
+    rng: StdRng,
+}
+
+impl QLearner {
+    pub fn new(epsilon: f64, alpha: f32, gamma: f32) -> Self {
+        Self {
+            table: HashMap::new(),
+            epsilon,
+            alpha,
+            gamma,
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+
+    /// How many states have been visited
+    pub fn states(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Choose an action, the best known one except with a chance of `epsilon` of a random one
+    pub fn act(&mut self, observation: &Observation, epsilon: f64) -> Action {
+        if self.rng.gen_bool(epsilon) {
+            Action::Discrete(self.rng.gen_range(0..DISCRETE_ACTIONS))
+        } else {
+            let values = self.values(state(observation));
+            let best = (0..DISCRETE_ACTIONS)
+                .max_by(|a, b| values[*a].total_cmp(&values[*b]))
+                .unwrap();
+            Action::Discrete(best)
+        }
+    }
+
+    /// Move the value of taking the action from the observed state towards the reward plus the discounted value of
+    /// the best action from the next state
+    pub fn learn(
+        &mut self,
+        observation: &Observation,
+        action: usize,
+        reward: f32,
+        next: &Observation,
+        done: bool,
+    ) {
+        let future = if done {
+            0.
+        } else {
+            self.values(state(next))
+                .into_iter()
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+        let target = reward + self.gamma * future;
+        let value = &mut self
+            .table
+            .entry(state(observation))
+            .or_insert([0.; DISCRETE_ACTIONS])[action];
+        *value += self.alpha * (target - *value);
+    }
+
+    /// Play an episode with each seed from 0 up to `episodes`, learning from every learner's steps. After each episode
+    /// `on_episode` is passed how it went
+    pub fn train(
+        &mut self,
+        gym: &mut TagGym,
+        episodes: u64,
+        mut on_episode: impl FnMut(u64, &Episode),
+    ) -> Result<()> {
+        for episode in 0..episodes {
+            let epsilon = self.epsilon * (1. - 0.9 * episode as f64 / episodes as f64);
+            let mut observations = gym.reset(episode);
+            let mut summary = Episode::new(observations.len());
+            loop {
+                let actions: Vec<_> = observations
+                    .iter()
+                    .map(|observation| self.act(observation, epsilon))
+                    .collect();
+                let result = gym.step(&actions)?;
+                for (learner, action) in actions.into_iter().enumerate() {
+                    let Action::Discrete(action) = action else {
+                        unreachable!("Only discrete actions are chosen")
+                    };
+                    self.learn(
+                        &observations[learner],
+                        action,
+                        result.rewards[learner],
+                        &result.observations[learner],
+                        result.done,
+                    );
+                }
+                summary.record(&result);
+                observations = result.observations;
+                if result.done {
+                    break;
+                }
+            }
+            on_episode(episode, &summary);
+        }
+        Ok(())
+    }
+
+    fn values(&self, state: usize) -> [f32; DISCRETE_ACTIONS] {
+        self.table
+            .get(&state)
+            .copied()
+            .unwrap_or([0.; DISCRETE_ACTIONS])
+    }
+}
+
+/// How an episode went
+#[derive(Clone, Debug, PartialEq)]
+pub struct Episode {
+    /// The total reward of each learner
+    pub rewards: Vec<f32>,
+    pub steps: u64,
+    /// Tags made by anyone
+    pub tags: usize,
+}
+
+impl Episode {
+    fn new(learners: usize) -> Self {
+        Self {
+            rewards: vec![0.; learners],
+            steps: 0,
+            tags: 0,
+        }
+    }
+
+    fn record(&mut self, result: &StepResult) {
+        for (total, reward) in self.rewards.iter_mut().zip(&result.rewards) {
+            *total += reward;
+        }
+        self.steps = result.info.step;
+        self.tags += result
+            .info
+            .events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::Tagged { .. }))
+            .count();
+    }
+
+    /// The mean total reward of the learners
+    pub fn mean_reward(&self) -> f32 {
+        self.rewards.iter().sum::<f32>() / self.rewards.len() as f32
+    }
+}
+
+/// Play an episode with each seed from 0 up to `episodes` with the learners running and interacting at random, as a
+/// baseline for what is learned
+pub fn random_baseline(gym: &mut TagGym, episodes: u64) -> Result<Vec<Episode>> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut summaries = Vec::new();
+    for episode in 0..episodes {
+        let learners = gym.reset(episode).len();
+        let mut summary = Episode::new(learners);
+        loop {
+            let actions: Vec<_> = (0..learners)
+                .map(|_| Action::Continuous([0.; 3].map(|_| rng.gen_range(-1.0..=1.0))))
+                .collect();
+            let result = gym.step(&actions)?;
+            summary.record(&result);
+            if result.done {
+                break;
+            }
+        }
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
+
+/// Reduce an observation to one of a small number of states
+fn state(observation: &Observation) -> usize {
+    let own = observation[2] as usize + 2 * observation[3] as usize;
+    let closest = &observation[CLOSEST..];
+    let neighbour = if closest[0] == 0. {
+        0
+    } else {
+        let angle = closest[2].atan2(closest[1]);
+        let octant = ((angle / std::f32::consts::FRAC_PI_4).round() as i32).rem_euclid(8) as usize;
+        let distance = if closest[6] == 1. {
+            0
+        } else if closest[1].hypot(closest[2]) < CLOSE {
+            1
+        } else {
+            2
+        };
+        1 + octant + 8 * (distance + 3 * closest[3] as usize)
+    };
+    let edge = |fraction: f32| {
+        if fraction < CLOSE {
+            0
+        } else if fraction > 1. - CLOSE {
+            2
+        } else {
+            1
+        }
+    };
+    let edges = edge(observation[0]) + 3 * edge(observation[1]);
+    own + 4 * (neighbour + 49 * edges)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::observation::OBSERVATION_SIZE;
+
+    #[test]
+    fn learn_values() {
+        let mut learner = QLearner::new(0., 0.5, 0.9);
+        let observation = [0.5; OBSERVATION_SIZE];
+        let mut next = observation;
+        next[0] = 0.;
+        learner.learn(&next, 4, 2., &next, true);
+        assert_eq!(learner.values(state(&next))[4], 1.);
+
+        learner.learn(&observation, 3, 1., &next, false);
+        assert_eq!(
+            learner.values(state(&observation))[3],
+            0.5 * (1. + 0.9 * 1.)
+        );
+        assert_eq!(learner.act(&observation, 0.), Action::Discrete(3));
+        assert_eq!(learner.states(), 2);
+    }
+
+    #[test]
+    fn states_differ() {
+        let mut observation = [0.; OBSERVATION_SIZE];
+        observation[..2].copy_from_slice(&[0.5, 0.5]);
+        let alone = state(&observation);
+        observation[CLOSEST..CLOSEST + 3].copy_from_slice(&[1., 0.05, 0.]);
+        let east = state(&observation);
+        observation[CLOSEST + 1..CLOSEST + 3].copy_from_slice(&[0., -0.05]);
+        let south = state(&observation);
+        observation[CLOSEST + 3] = 1.;
+        let south_it = state(&observation);
+        let states = [alone, east, south, south_it];
+        for (i, a) in states.iter().enumerate() {
+            assert!(states[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
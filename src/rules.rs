@@ -0,0 +1,95 @@
+use serde::Serialize;
+
+use crate::environment::PlayerDistance;
+
+/// The rules of the game, the referee enforces them and the players can read them to decide what to do
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rules {
+    /// What happens when a player is tagged
+    pub mode: GameMode,
+    /// How far a player can reach to tag or unfreeze another player
+    pub tag_reach: PlayerDistance,
+    /// Whether a player who has just been tagged can tag straight back the player who tagged them
+    pub tag_back: TagBack,
+    /// How many steps a newly tagged player has to wait before they can tag anyone
+    pub tag_cooldown: u64,
+    /// How many steps a newly tagged player has to stand still for before they can start chasing
+    pub freeze_steps: u64,
+    /// How far a player can run each step
+    pub max_speed: PlayerDistance,
+    /// When the game has been won
+    pub win_condition: WinCondition,
+    /// Who becomes it when the last player who is it leaves the game
+    pub succession: Succession,
+}
+
+impl Rules {
+    /// The usual rules for a game mode
+    pub fn new(mode: GameMode) -> Self {
+        Self {
+            mode,
+            tag_reach: 1.,
+            tag_back: TagBack::Never,
+            tag_cooldown: 0,
+            freeze_steps: 0,
+            max_speed: 2.,
+            win_condition: match mode {
+                GameMode::Classic => WinCondition::Never,
+                GameMode::Freeze | GameMode::Infection | GameMode::Teams => WinCondition::AllCaught,
+            },
+            succession: Succession::Nearest,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new(GameMode::default())
+    }
+}
+
+/// The rule set which decides what happens when a player is tagged
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    /// The tagged player becomes it and the tagger is no longer it
+    #[default]
+    Classic,
+    /// The tagged player is frozen in place until another player unfreezes them
+    Freeze,
+    /// The tagged player becomes it as well as the tagger
+    Infection,
+    /// Nobody is it, anyone can tag a player from another team who is then held in their team's jail until a team
+    /// mate frees them
+    Teams,
+}
+
+/// Whether it can tag back the player who just tagged them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TagBack {
+    /// It can tag anyone straight away
+    Allowed,
+    /// It can tag anyone except the player who tagged them
+    Never,
+    /// It can tag back the player who tagged them once this many steps have passed
+    AfterSteps(u64),
+}
+
+/// When the game has been won
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WinCondition {
+    /// The game goes on forever
+    Never,
+    /// The game is won once everyone has been caught: when everyone is frozen in freeze tag, everyone is it in
+    /// infection and only one team has free players in team tag. Classic tag can't be won this way.
+    AllCaught,
+}
+
+/// Who becomes it when the last player who is it leaves the game, only players who aren't frozen are chosen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Succession {
+    /// The player closest to where it left
+    Nearest,
+    /// A player chosen at random
+    Random,
+}
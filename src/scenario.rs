@@ -0,0 +1,103 @@
+use euclid::default::Point2D;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    rules::{GameMode, Rules},
+    simulation::{EndCondition, Simulation},
+};
+
+/// The setup for a game, everything but the players' strategies and where they start
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub area: PlayArea,
+    pub rules: Rules,
+    pub player_count: usize,
+    /// How many teams the players are split into in team tag
+    pub team_count: usize,
+    /// The game always ends after this many steps
+    pub step_limit: u64,
+    /// Further conditions which end the game
+    pub end_conditions: Vec<EndCondition>,
+}
+
+impl Scenario {
+    /// The team a player is on, everyone is on the same team except in team tag
+    pub fn team(&self, player_id: PlayerId) -> TeamId {
+        if self.rules.mode == GameMode::Teams {
+            player_id % self.team_count
+        } else {
+            0
+        }
+    }
+
+    /// Which of two sides a player is on when two strategies play each other, the teams in team tag and otherwise
+    /// alternating players
+    pub fn side(&self, player_id: PlayerId) -> usize {
+        if self.rules.mode == GameMode::Teams {
+            self.team(player_id)
+        } else {
+            player_id % 2
+        }
+    }
+
+    /// Where the players start and their status, with the positions chosen at random by the seed
+    pub fn player_state(&self, seed: u64) -> Vec<TagPlayerVisibleState> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..self.player_count)
+            .map(|player_id| {
+                // in team tag nobody is it, otherwise the first player starts as it
+                let status = if self.rules.mode != GameMode::Teams && player_id == 0 {
+                    TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    }
+                } else {
+                    TagStatus::NotIt
+                };
+                TagPlayerVisibleState {
+                    position: random_position(&mut rng, &self.area),
+                    status,
+                    team: self.team(player_id),
+                }
+            })
+            .collect()
+    }
+
+    /// Where a player who joins part way through the game starts, not it and at a random position chosen by the seed
+    /// and their id
+    pub fn late_player_state(&self, seed: u64, player_id: PlayerId) -> TagPlayerVisibleState {
+        let mut rng = StdRng::seed_from_u64(seed ^ (player_id as u64).rotate_left(32));
+        TagPlayerVisibleState {
+            position: random_position(&mut rng, &self.area),
+            status: TagStatus::NotIt,
+            team: self.team(player_id),
+        }
+    }
+
+    /// Set up a simulation with the players starting at random positions chosen by the seed, each player is played
+    /// by the agent returned for them
+    pub fn simulation(
+        &self,
+        seed: u64,
+        mut agent: impl FnMut(PlayerId) -> Box<dyn Agent>,
+    ) -> Simulation {
+        let players = self
+            .player_state(seed)
+            .into_iter()
+            .enumerate()
+            .map(|(player_id, state)| (agent(player_id), state));
+        self.end_conditions.iter().cloned().fold(
+            Simulation::new(self.area, self.rules, players)
+                .seed(seed)
+                .end_when(EndCondition::StepLimit(self.step_limit)),
+            Simulation::end_when,
+        )
+    }
+}
+
+/// Select a random position within the play area
+fn random_position(rng: &mut StdRng, area: &PlayArea) -> Position {
+    Point2D::new(rng.gen_range(area.x_range()), rng.gen_range(area.y_range()))
+}
@@ -0,0 +1,358 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use rhai::{Engine, EvalAltResult, FnPtr, NativeCallContext, Scope, AST, FLOAT, INT};
+
+use crate::{agent::Agent, environment::*, rules::GameMode};
+
+type RunStretch = euclid::default::Vector2D<f32>;
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// How many operations a script can run for each action, so a script stuck in a loop can't stop the game
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Plays by running a Rhai script, which has to define `fn act(game)` returning `run(dx, dy)`, `tag(id)`,
+/// `unfreeze(id)` or `stand()`. The script is reloaded whenever the file changes, if the new version doesn't compile
+/// the old one keeps playing
+#[derive(Debug)]
+pub struct ScriptAgent {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    /// When the loaded version of the file was modified
+    modified: Option<SystemTime>,
+}
+
+impl ScriptAgent {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        let engine = engine();
+        let modified = modified(&path);
+        let ast = compile(&engine, &path)?;
+        Ok(Self {
+            path,
+            engine,
+            ast,
+            modified,
+        })
+    }
+
+    /// Load the file again if it has changed since it was loaded. If the new version doesn't compile the previous
+    /// version is kept, and the error is returned once
+    fn reload(&mut self) -> Result<()> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(());
+        }
+        self.modified = modified;
+        self.ast = compile(&self.engine, &self.path)
+            .map_err(|error| format!("Keeping the previous version of the script: {}", error))?;
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<AST> {
+    let source = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read script '{}': {}", path.display(), error))?;
+    let ast = engine
+        .compile(source)
+        .map_err(|error| format!("Invalid script '{}': {}", path.display(), error))?;
+    if !ast
+        .iter_functions()
+        .any(|f| f.name == "act" && f.params.len() == 1)
+    {
+        return Err(format!("Script '{}' has no fn act(game)", path.display()).into());
+    }
+    Ok(ast)
+}
+
+impl Agent for ScriptAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        self.reload()?;
+        let game = Game(Arc::new(Snapshot::new(environment, player_id)));
+        let ScriptAction(action) = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "act", (game,))
+            .map_err(|error| format!("Script '{}' failed: {}", self.path.display(), error))?;
+        Ok(action)
+    }
+}
+
+/// A copy of what a player can see of the environment, which is all a script can read
+#[derive(Debug)]
+struct Snapshot {
+    me: PlayerId,
+    step: u64,
+    area: PlayArea,
+    mode: GameMode,
+    reach: f32,
+    max_speed: f32,
+    players: Vec<PlayerSnapshot>,
+}
+
+#[derive(Debug)]
+struct PlayerSnapshot {
+    position: Position,
+    it: bool,
+    frozen: bool,
+    team: TeamId,
+    /// Whether the player running the script isn't yet allowed to tag them back
+    tag_back_banned: bool,
+    /// Whether the player is still in the game
+    playing: bool,
+}
+
+impl Snapshot {
+    fn new(environment: &TagEnvironment, me: PlayerId) -> Self {
+        let rules = environment.rules();
+        Self {
+            me,
+            step: environment.step(),
+            area: environment.area(),
+            mode: rules.mode,
+            reach: rules.tag_reach,
+            max_speed: rules.max_speed,
+            players: environment
+                .player_state()
+                .iter()
+                .enumerate()
+                .map(|(player_id, state)| PlayerSnapshot {
+                    position: state.position,
+                    it: state.is_it(),
+                    frozen: state.is_frozen(),
+                    team: state.team,
+                    tag_back_banned: environment.is_tag_back_banned(me, player_id),
+                    playing: environment.is_playing(player_id),
+                })
+                .collect(),
+        }
+    }
+
+    fn player(&self, player_id: INT) -> ScriptResult<&PlayerSnapshot> {
+        usize::try_from(player_id)
+            .ok()
+            .and_then(|player_id| self.players.get(player_id))
+            .filter(|player| player.playing)
+            .ok_or_else(|| format!("There is no player {}", player_id).into())
+    }
+}
+
+/// The script's read only view of the game
+#[derive(Clone, Debug)]
+struct Game(Arc<Snapshot>);
+
+/// An action returned by a script
+#[derive(Clone, Debug)]
+struct ScriptAction(TagPlayerAction);
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    // the defaults are lower in debug builds, too low for a strategy of a few nested blocks
+    engine.set_max_expr_depths(64, 64);
+    // printing would write over the viewer's screen
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+        .register_type_with_name::<Game>("Game")
+        .register_get("me", |game: &mut Game| game.0.me as INT)
+        .register_get("step", |game: &mut Game| game.0.step as INT)
+        .register_get("player_count", |game: &mut Game| {
+            game.0.players.len() as INT
+        })
+        .register_get("mode", |game: &mut Game| {
+            match game.0.mode {
+                GameMode::Classic => "classic",
+                GameMode::Freeze => "freeze",
+                GameMode::Infection => "infection",
+                GameMode::Teams => "teams",
+            }
+            .to_owned()
+        })
+        .register_get("reach", |game: &mut Game| game.0.reach as FLOAT)
+        .register_get("max_speed", |game: &mut Game| game.0.max_speed as FLOAT)
+        .register_get("min_x", |game: &mut Game| game.0.area.min_x() as FLOAT)
+        .register_get("min_y", |game: &mut Game| game.0.area.min_y() as FLOAT)
+        .register_get("max_x", |game: &mut Game| game.0.area.max_x() as FLOAT)
+        .register_get("max_y", |game: &mut Game| game.0.area.max_y() as FLOAT)
+        .register_fn("is_playing", |game: &mut Game, player_id: INT| {
+            game.0.player(player_id).is_ok()
+        })
+        .register_fn("x", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.position.x as FLOAT)
+        })
+        .register_fn("y", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.position.y as FLOAT)
+        })
+        .register_fn("is_it", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.it)
+        })
+        .register_fn("is_frozen", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.frozen)
+        })
+        .register_fn("team", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.team as INT)
+        })
+        .register_fn("is_tag_back_banned", |game: &mut Game, player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(game.0.player(player_id)?.tag_back_banned)
+        })
+        .register_fn("is_within_reach", |game: &mut Game, a: INT, b: INT| {
+            let (a, b) = (game.0.player(a)?, game.0.player(b)?);
+            Ok::<_, Box<EvalAltResult>>(
+                (a.position - b.position).square_length() <= game.0.reach * game.0.reach,
+            )
+        })
+        .register_fn(
+            "closest_player_except",
+            |game: &mut Game, player_id: INT, ignore: INT| {
+                closest(game, player_id, |other_id| Ok(other_id != ignore))
+            },
+        )
+        .register_fn(
+            "closest_player_where",
+            |context: NativeCallContext, game: Game, player_id: INT, predicate: FnPtr| {
+                closest(&game, player_id, |other_id| {
+                    predicate.call_within_context(&context, (other_id,))
+                })
+            },
+        )
+        .register_type_with_name::<ScriptAction>("Action")
+        .register_fn("stand", || {
+            ScriptAction(TagPlayerAction::Run {
+                stretch: RunStretch::zero(),
+            })
+        })
+        .register_fn("run", |dx: FLOAT, dy: FLOAT| {
+            ScriptAction(TagPlayerAction::Run {
+                stretch: RunStretch::new(dx as f32, dy as f32),
+            })
+        })
+        .register_fn("tag", |player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(ScriptAction(TagPlayerAction::Tag {
+                player_id: to_player_id(player_id)?,
+            }))
+        })
+        .register_fn("unfreeze", |player_id: INT| {
+            Ok::<_, Box<EvalAltResult>>(ScriptAction(TagPlayerAction::Unfreeze {
+                player_id: to_player_id(player_id)?,
+            }))
+        });
+    engine
+}
+
+fn to_player_id(player_id: INT) -> ScriptResult<PlayerId> {
+    usize::try_from(player_id).map_err(|_| format!("There is no player {}", player_id).into())
+}
+
+/// The closest other player to a player out of those matching a predicate, or -1 if there are none
+fn closest(
+    game: &Game,
+    player_id: INT,
+    mut predicate: impl FnMut(INT) -> ScriptResult<bool>,
+) -> ScriptResult<INT> {
+    let position = game.0.player(player_id)?.position;
+    let mut closest = None;
+    for (other_id, other) in game.0.players.iter().enumerate() {
+        let other_id = other_id as INT;
+        if other_id == player_id || !other.playing || !predicate(other_id)? {
+            continue;
+        }
+        let sq_distance = (other.position - position).square_length();
+        if closest.is_none_or(|(_, shortest)| sq_distance < shortest) {
+            closest = Some((other_id, sq_distance));
+        }
+    }
+    Ok(closest.map_or(-1, |(other_id, _)| other_id))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, time::Duration};
+
+    use super::*;
+
+    fn environment() -> TagEnvironment {
+        test_environment((10., 10.), &[(1., 1.), (5., 5.), (1.5, 1.)])
+    }
+
+    /// Write a script with a distinct modified time, as the file system's resolution may be too coarse to tell
+    /// versions written straight after each other apart
+    fn write_script(path: &Path, source: &str, version: u64) {
+        fs::write(path, source).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(version))
+            .unwrap();
+    }
+
+    #[test]
+    fn script_reads_the_game() {
+        let path = std::env::temp_dir().join(format!("tag-script-{}.rhai", std::process::id()));
+        write_script(
+            &path,
+            r#"
+            fn act(game) {
+                let me = game.me;
+                if game.is_it(me) {
+                    let target = closest_player_where(game, me, |id| !game.is_it(id));
+                    if game.is_within_reach(me, target) { return tag(target); }
+                    return run(game.x(target) - game.x(me), game.y(target) - game.y(me));
+                }
+                let chaser = game.closest_player_except(me, -1);
+                run(game.x(me) - game.x(chaser), game.max_y - game.y(me))
+            }
+            "#,
+            1,
+        );
+        let mut agent = ScriptAgent::load(&path).unwrap();
+        let environment = environment();
+        assert_eq!(
+            agent.act(0, &environment).unwrap(),
+            TagPlayerAction::Tag { player_id: 2 }
+        );
+        assert_eq!(
+            agent.act(1, &environment).unwrap(),
+            TagPlayerAction::Run {
+                stretch: RunStretch::new(3.5, 5.)
+            }
+        );
+
+        write_script(&path, "fn act(game) { unfreeze(-1) }", 2);
+        assert!(agent.act(1, &environment).is_err());
+        write_script(&path, "fn act(game) { this won't compile", 3);
+        let error = agent.act(1, &environment).unwrap_err();
+        assert!(error.to_string().contains("previous version"));
+        assert!(
+            agent.act(1, &environment).is_err(),
+            "keeps the last version"
+        );
+        write_script(&path, "fn act(game) { stand() }", 4);
+        assert_eq!(
+            agent.act(1, &environment).unwrap(),
+            TagPlayerAction::Run {
+                stretch: RunStretch::zero()
+            }
+        );
+
+        write_script(&path, "fn act(game) { loop {} }", 5);
+        assert!(ScriptAgent::load(&path)
+            .unwrap()
+            .act(0, &environment)
+            .is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use euclid::default::Vector2D;
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    protocol::{ServerMessage, StepRequest, StepResponse, PROTOCOL_VERSION},
+    simulation::GameOutcome,
+};
+
+/// How long writing to a client can block before it is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Lets clients on the network control players, with the [`ServerMessage`]s and [`StepResponse`]s of the protocol as
+/// lines of JSON over TCP. Each player which clients can control is played by a fallback agent while nobody is
+/// connected to it, including after its client disconnects
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    slots: Vec<Slot>,
+}
+
+/// A player which a client can control
+#[derive(Debug)]
+struct Slot {
+    player_id: PlayerId,
+    client: Arc<Mutex<Option<Client>>>,
+    /// Where the client was connected from when the server last checked, to notice when it has gone
+    address: Option<SocketAddr>,
+}
+
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    address: SocketAddr,
+    /// Answers read from the client on another thread, or why a line couldn't be read
+    responses: Receiver<std::result::Result<StepResponse, String>>,
+    /// The latest answer read for the step, which the player hasn't acted on yet
+    pending: Option<Answer>,
+}
+
+/// What a client has answered for a step
+#[derive(Debug)]
+enum Answer {
+    Action(TagPlayerAction),
+    /// Nothing yet, the player stands still
+    Waiting,
+    Invalid(String),
+    Disconnected,
+}
+
+impl Server {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            slots: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Let clients control a player, the agent returned plays them and uses the fallback while nobody is connected
+    pub fn slot(&mut self, player_id: PlayerId, fallback: Box<dyn Agent>) -> RemoteAgent {
+        let client = Arc::new(Mutex::new(None));
+        self.slots.push(Slot {
+            player_id,
+            client: client.clone(),
+            address: None,
+        });
+        RemoteAgent { client, fallback }
+    }
+
+    /// Give new connections the free players who are still playing, disconnect the clients of players who have been
+    /// removed and notice clients which have gone, describing what happened
+    pub fn poll(&mut self, environment: &TagEnvironment) -> Vec<String> {
+        let mut news = Vec::new();
+        for slot in &mut self.slots {
+            if !environment.is_playing(slot.player_id) {
+                if let Some(mut client) = slot.client.lock().unwrap().take() {
+                    let _ = client.send(&ServerMessage::Removed {
+                        player_id: slot.player_id,
+                    });
+                    news.push(format!(
+                        "Client {} disconnected, player {} was removed",
+                        client.address, slot.player_id
+                    ));
+                }
+                slot.address = None;
+                continue;
+            }
+            let address = slot
+                .client
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|client| client.address);
+            if let (Some(gone), None) = (slot.address, address) {
+                news.push(format!(
+                    "Client {} disconnected from player {}",
+                    gone, slot.player_id
+                ));
+            }
+            slot.address = address;
+        }
+        while let Ok((mut stream, address)) = self.listener.accept() {
+            let Some(slot) = self
+                .slots
+                .iter_mut()
+                .find(|slot| slot.address.is_none() && environment.is_playing(slot.player_id))
+            else {
+                let full = ServerMessage::Full {
+                    version: PROTOCOL_VERSION,
+                };
+                let _ = send(&mut stream, &full);
+                continue;
+            };
+            let welcome = ServerMessage::Welcome {
+                version: PROTOCOL_VERSION,
+                player_id: slot.player_id,
+            };
+            match Client::new(stream, address)
+                .and_then(|mut client| client.send(&welcome).map(|_| client))
+            {
+                Ok(client) => {
+                    news.push(format!(
+                        "Client {} connected to player {}",
+                        address, slot.player_id
+                    ));
+                    slot.address = Some(address);
+                    *slot.client.lock().unwrap() = Some(client);
+                }
+                Err(error) => news.push(format!("Failed to set up client {}: {}", address, error)),
+            }
+        }
+        news
+    }
+
+    /// Send each client the state of the game for the next step
+    pub fn broadcast(&mut self, environment: &TagEnvironment) {
+        for slot in &self.slots {
+            let request = StepRequest::new(environment, slot.player_id);
+            let mut client = slot.client.lock().unwrap();
+            if let Some(connected) = client.as_mut() {
+                if connected.send(&ServerMessage::Step(request)).is_err() {
+                    *client = None;
+                }
+            }
+        }
+    }
+
+    /// Tell the clients the game is over and disconnect them
+    pub fn finish(&mut self, outcome: &GameOutcome) {
+        let message = ServerMessage::GameOver {
+            steps: outcome.steps,
+            scores: outcome
+                .statistics
+                .players()
+                .iter()
+                .map(|player| player.steps_free)
+                .collect(),
+        };
+        for slot in &self.slots {
+            if let Some(mut client) = slot.client.lock().unwrap().take() {
+                let _ = client.send(&message);
+            }
+        }
+    }
+}
+
+impl Client {
+    fn new(stream: TcpStream, address: SocketAddr) -> io::Result<Self> {
+        // accepted connections may inherit the listener's non blocking mode
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, responses) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let response = serde_json::from_str(&line)
+                    .map_err(|error| format!("Invalid answer '{}': {}", line, error));
+                if sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            stream,
+            address,
+            responses,
+            pending: None,
+        })
+    }
+
+    fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        send(&mut self.stream, message)
+    }
+
+    /// The latest answer for the step, skipping answers for earlier steps
+    fn answer(&mut self, step: u64) -> Answer {
+        self.receive(step);
+        self.pending.take().unwrap_or(Answer::Waiting)
+    }
+
+    /// Read the answers which have arrived, keeping the latest for the step as pending
+    fn receive(&mut self, step: u64) {
+        loop {
+            match self.responses.try_recv() {
+                Ok(Ok(response)) if response.step == step => {
+                    self.pending = Some(Answer::Action(response.action.into()))
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(error)) => self.pending = Some(Answer::Invalid(error)),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.pending = Some(Answer::Disconnected);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn send(stream: &mut TcpStream, message: &ServerMessage) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+/// Plays a player the way their client answers, or with the fallback agent while no client is connected
+#[derive(Debug)]
+pub struct RemoteAgent {
+    client: Arc<Mutex<Option<Client>>>,
+    fallback: Box<dyn Agent>,
+}
+
+impl Agent for RemoteAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        let mut client = self.client.lock().unwrap();
+        if let Some(connected) = client.as_mut() {
+            match connected.answer(environment.step()) {
+                Answer::Action(action) => return Ok(action),
+                Answer::Waiting => {
+                    return Ok(TagPlayerAction::Run {
+                        stretch: Vector2D::zero(),
+                    })
+                }
+                Answer::Invalid(error) => return Err(error.into()),
+                Answer::Disconnected => *client = None,
+            }
+        }
+        drop(client);
+        self.fallback.act(player_id, environment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use euclid::default::Rect;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{agent::NaiveAgent, rules::Rules, simulation::Simulation};
+
+    fn read_message(reader: &mut impl BufRead) -> Value {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn clients_control_players() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let remote = server.slot(1, Box::new(NaiveAgent));
+        let mut simulation = Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            Rules::default(),
+            [
+                (
+                    Box::new(NaiveAgent) as Box<dyn Agent>,
+                    TagPlayerVisibleState {
+                        position: (10., 10.).into(),
+                        status: TagStatus::It {
+                            tagged_by: 0,
+                            tagged_at: 0,
+                        },
+                        team: 0,
+                    },
+                ),
+                (
+                    Box::new(remote),
+                    TagPlayerVisibleState {
+                        position: (50., 50.).into(),
+                        status: TagStatus::NotIt,
+                        team: 0,
+                    },
+                ),
+            ],
+        );
+
+        let mut client = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        assert_eq!(server.poll(simulation.environment()).len(), 1);
+        assert_eq!(read_message(&mut reader)["welcome"]["player_id"], 1);
+        // the only player is taken
+        let other = TcpStream::connect(address).unwrap();
+        assert!(server.poll(simulation.environment()).is_empty());
+        assert!(read_message(&mut BufReader::new(other))["full"].is_object());
+
+        server.broadcast(simulation.environment());
+        let request = read_message(&mut reader);
+        assert_eq!(request["step"]["player_id"], 1);
+        assert_eq!(request["step"]["version"], PROTOCOL_VERSION);
+        writeln!(
+            client,
+            r#"{{"step": 0, "action": {{"run": {{"dx": 0, "dy": 2}}}}}}"#
+        )
+        .unwrap();
+        // wait for the answer to arrive
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !server.slots[0]
+            .client
+            .lock()
+            .unwrap()
+            .as_mut()
+            .is_some_and(|client| {
+                client.receive(0);
+                client.pending.is_some()
+            })
+        {
+            assert!(Instant::now() < deadline, "The answer never arrived");
+            thread::sleep(Duration::from_millis(1));
+        }
+        simulation.step().unwrap();
+        assert_eq!(simulation.player_state()[1].position, (50., 52.).into());
+
+        // the fallback takes over once the client has gone
+        drop(reader);
+        drop(client);
+        while server.slots[0].client.lock().unwrap().is_some() {
+            simulation.step().unwrap();
+        }
+        assert_eq!(server.poll(simulation.environment()).len(), 1);
+        assert!(simulation.outcome().agent_failures.is_empty());
+
+        // once the player has been removed their client is told, and nobody else can take them
+        let mut reader = BufReader::new(TcpStream::connect(address).unwrap());
+        assert_eq!(server.poll(simulation.environment()).len(), 1);
+        assert_eq!(read_message(&mut reader)["welcome"]["player_id"], 1);
+        simulation.remove_player(1);
+        assert_eq!(server.poll(simulation.environment()).len(), 1);
+        assert_eq!(read_message(&mut reader)["removed"]["player_id"], 1);
+        let late = TcpStream::connect(address).unwrap();
+        assert!(server.poll(simulation.environment()).is_empty());
+        assert!(read_message(&mut BufReader::new(late))["full"].is_object());
+    }
+}
@@ -1,54 +1,269 @@
-use crate::{agent::TagPlayerAgent, environment::*};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    rules::{GameMode, Rules, Succession},
+    stats::Statistics,
+    tag_graph::TagGraph,
+};
+use euclid::default::Vector2D;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use rayon::prelude::*;
 
 /// Simulation runner
 #[derive(Debug)]
 pub struct Simulation {
     actions: Vec<TagPlayerAction>,
-    agents: Vec<TagPlayerAgent>,
+    /// The agent playing each player, by their id, players who have left have none
+    agents: Vec<Option<Box<dyn Agent>>>,
     environment: TagEnvironment,
-    step: u64,
+    /// The game stops as soon as any of these are met
+    end_conditions: Vec<EndCondition>,
+    /// Why the game stopped, once it has
+    ended: Option<EndReason>,
+    /// When the first step was started
+    started_at: Option<Instant>,
+    /// Statistics about how each player has played
+    statistics: Statistics,
+    /// Who has tagged whom
+    tag_graph: TagGraph,
+    /// The step on which the game was won, if it has been
+    won_at: Option<u64>,
+    /// What to do when an agent fails
+    on_failure: FailurePolicy,
+    /// Times each player's agent has failed or broken the rules
+    failures: Vec<AgentFailures>,
+    /// Events which happened during the last step, including agent failures
+    events: Vec<GameEvent>,
+    /// Players joining and leaving since the last step, reported with the next step's events
+    roster_events: Vec<GameEvent>,
+    /// Chooses who takes over as it at random
+    rng: StdRng,
+}
+
+/// Times a player's agent failed or broke the rules
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AgentFailures {
+    pub count: u64,
+    /// Why the agent last failed
+    pub last_error: String,
+}
+
+/// What the simulation does when an agent fails to choose a valid action
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// The player's turn is skipped and they stand still
+    #[default]
+    Skip,
+    /// Ask the agent again up to this many times, then skip the turn
+    Retry(u32),
+    /// Take the player out of the game
+    Remove,
+    /// Stop the simulation, [`Simulation::step`] returns the error
+    Abort,
 }
 
 impl Simulation {
-    /// Create a new simulation specifying the playing area and an iterator to generate all the players
+    /// Create a new simulation specifying the playing area, the rules and an iterator to generate all the players.
+    /// The game ends once it has been won, further end conditions can be added with [`Simulation::end_when`]
     pub fn new(
         area: PlayArea,
-        players: impl IntoIterator<Item = (TagPlayerAgent, TagPlayerVisibleState)>,
+        rules: Rules,
+        players: impl IntoIterator<Item = (Box<dyn Agent>, TagPlayerVisibleState)>,
     ) -> Self {
         let (agents, player_state): (Vec<_>, Vec<_>) = players.into_iter().unzip();
+        let environment = TagEnvironment::new(area, rules, player_state);
         Self {
             actions: Vec::with_capacity(agents.len()),
-            agents,
-            environment: TagEnvironment::new(area, player_state),
-            step: 0,
+            agents: agents.into_iter().map(Some).collect(),
+            statistics: Statistics::new(&environment),
+            tag_graph: TagGraph::new(environment.player_state().len()),
+            environment,
+            end_conditions: vec![EndCondition::Won],
+            ended: None,
+            started_at: None,
+            won_at: None,
+            on_failure: FailurePolicy::default(),
+            failures: Vec::new(),
+            events: Vec::new(),
+            roster_events: Vec::new(),
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
+    /// Seed the random choices the simulation makes, such as who takes over as it
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Choose what happens when an agent fails, by default their turn is skipped
+    pub fn on_failure(mut self, policy: FailurePolicy) -> Self {
+        self.on_failure = policy;
+        self
+    }
+
+    /// Also end the game when a condition is met
+    pub fn end_when(mut self, condition: EndCondition) -> Self {
+        self.end_conditions.push(condition);
+        self
+    }
+
     /// Step the simulation:
     ///
-    /// 1. Ask each agent to choose it's action based on the current environment
-    /// 2. Apply the actions to the environment
-    /// 3. Increment step counter
-    pub fn step(&mut self) {
+    /// 1. Ask each agent to choose it's action based on the current environment, handling failures by the
+    ///    [`FailurePolicy`]
+    /// 2. Apply the actions to the environment, which increments the step counter
+    /// 3. Record statistics and the tag graph
+    /// 4. Check whether the game has ended
+    ///
+    /// Once the game has ended, stepping does nothing. An error is only returned when an agent fails with the
+    /// [`FailurePolicy::Abort`] policy, in which case the step isn't applied
+    pub fn step(&mut self) -> Result<StepStatus> {
+        if let Some(reason) = self.ended {
+            return Ok(StepStatus::Ended(reason));
+        }
+        self.started_at.get_or_insert_with(Instant::now);
+
+        let mut results = Vec::with_capacity(self.agents.len());
         self.agents
             .par_iter_mut()
             .enumerate()
-            .map(|(player_id, agent)| {
-                agent
-                    .act(player_id, &self.environment)
-                    .expect("Simulation cannot run when agent actions fail")
+            .map(|(player_id, agent)| match agent {
+                Some(agent) => choose_action(agent.as_mut(), player_id, &self.environment),
+                None => Ok(STAND_STILL),
             })
-            .collect_into_vec(&mut self.actions);
+            .collect_into_vec(&mut results);
+
+        self.events.clear();
+        self.actions.clear();
+        let mut removed = Vec::new();
+        for (player_id, mut result) in results.into_iter().enumerate() {
+            if let FailurePolicy::Retry(retries) = self.on_failure {
+                for _ in 0..retries {
+                    if result.is_ok() {
+                        break;
+                    }
+                    if let Some(agent) = &mut self.agents[player_id] {
+                        result = choose_action(agent.as_mut(), player_id, &self.environment);
+                    }
+                }
+            }
+            let error = match result {
+                Ok(action) => {
+                    self.actions.push(action);
+                    continue;
+                }
+                Err(error) => error,
+            };
+            self.events
+                .push(GameEvent::AgentFailed { player: player_id });
+            if self.failures.len() <= player_id {
+                self.failures
+                    .resize(player_id + 1, AgentFailures::default());
+            }
+            let failures = &mut self.failures[player_id];
+            failures.count += 1;
+            failures.last_error = error;
+            match self.on_failure {
+                FailurePolicy::Abort => {
+                    return Err(format!(
+                        "Player {}'s agent failed: {}",
+                        player_id, failures.last_error
+                    )
+                    .into())
+                }
+                FailurePolicy::Remove => removed.push(player_id),
+                FailurePolicy::Skip | FailurePolicy::Retry(_) => {}
+            }
+            self.actions.push(STAND_STILL);
+        }
 
         self.environment.apply_actions(&self.actions);
-        self.step += 1;
+        self.events.extend_from_slice(self.environment.events());
+        // players are removed after the step so that actions already chosen against them stay valid
+        for player_id in removed {
+            self.remove_player(player_id);
+        }
+        self.events.append(&mut self.roster_events);
+        self.statistics.record(&self.environment);
+        self.tag_graph.record(&self.environment);
+        if self.won_at.is_none() && self.environment.is_game_over() {
+            self.won_at = Some(self.environment.step());
+        }
+
+        self.ended = self
+            .end_conditions
+            .iter()
+            .find_map(|condition| condition.is_met(self).then(|| condition.reason()));
+        Ok(match self.ended {
+            Some(reason) => StepStatus::Ended(reason),
+            None => StepStatus::Running,
+        })
+    }
+
+    /// Add a player who joins part way through the game, played by the agent. They get the next id, which is
+    /// returned, and play from the next step
+    pub fn add_player(&mut self, agent: Box<dyn Agent>, state: TagPlayerVisibleState) -> PlayerId {
+        self.statistics.add_player(&state);
+        self.tag_graph.add_player();
+        let player_id = self.environment.add_player(state);
+        self.agents.push(Some(agent));
+        self.roster_events
+            .push(GameEvent::Joined { player: player_id });
+        player_id
+    }
+
+    /// Take a player out of the game and drop their agent, their id isn't reused. When the last player who is it
+    /// leaves, another player takes over by the rules' [`Succession`]
+    pub fn remove_player(&mut self, player_id: PlayerId) {
+        if !self.environment.is_playing(player_id) {
+            return;
+        }
+        let was_it = self.environment.get_state(player_id).is_it();
+        self.environment.remove_player(player_id);
+        self.agents[player_id] = None;
+        self.roster_events
+            .push(GameEvent::Removed { player: player_id });
+        if !was_it || self.environment.get_its().next().is_some() {
+            return;
+        }
+        let environment = &self.environment;
+        let successor = match environment.rules().succession {
+            Succession::Nearest => environment
+                .closest_player_where(player_id, |_, state| !state.is_frozen())
+                .map(|(successor, _)| successor),
+            Succession::Random => {
+                let candidates: Vec<_> = environment
+                    .players()
+                    .filter(|(_, state)| !state.is_frozen())
+                    .map(|(candidate, _)| candidate)
+                    .collect();
+                candidates.choose(&mut self.rng).copied()
+            }
+        };
+        if let Some(successor) = successor {
+            self.environment.make_it(successor);
+            self.roster_events
+                .push(GameEvent::Promoted { player: successor });
+        }
     }
 
     pub fn actions(&self) -> &[TagPlayerAction] {
         &self.actions
     }
 
+    /// Events which happened during the last step, including agents failing and players being removed
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
     pub fn player_state(&self) -> &[TagPlayerVisibleState] {
         self.environment.player_state()
     }
@@ -56,4 +271,429 @@ impl Simulation {
     pub fn environment(&self) -> &TagEnvironment {
         &self.environment
     }
+
+    /// Statistics about how each player has played so far
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// The history of who has tagged whom so far
+    pub fn tag_graph(&self) -> &TagGraph {
+        &self.tag_graph
+    }
+
+    /// The outcome of the game, or of the game so far if it hasn't ended yet
+    pub fn outcome(&self) -> GameOutcome {
+        GameOutcome {
+            mode: self.environment.rules().mode,
+            steps: self.environment.step(),
+            end_reason: self.ended,
+            won_at: self.won_at,
+            statistics: self.statistics.clone(),
+            it: self.environment.get_its().map(|(id, _)| id).collect(),
+            team_scores: self.environment.team_scores().to_vec(),
+            agent_failures: self.failures.clone(),
+        }
+    }
+}
+
+/// What a player does when their agent fails
+pub const STAND_STILL: TagPlayerAction = TagPlayerAction::Run {
+    stretch: Vector2D::new(0., 0.),
+};
+
+/// Ask an agent for its action and check that it's allowed
+pub fn choose_action(
+    agent: &mut dyn Agent,
+    player_id: PlayerId,
+    environment: &TagEnvironment,
+) -> std::result::Result<TagPlayerAction, String> {
+    agent
+        .act(player_id, environment)
+        .and_then(|action| {
+            environment.check_action(player_id, &action)?;
+            Ok(action)
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Parses `skip`, `retry:count`, `remove` or `abort`
+impl FromStr for FailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "skip" => Ok(FailurePolicy::Skip),
+            None if s == "remove" => Ok(FailurePolicy::Remove),
+            None if s == "abort" => Ok(FailurePolicy::Abort),
+            Some(("retry", retries)) => retries
+                .parse()
+                .map(FailurePolicy::Retry)
+                .map_err(|_| format!("Invalid number of retries '{}'", retries)),
+            _ => Err(format!("Unknown failure policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for FailurePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailurePolicy::Skip => f.write_str("skip"),
+            FailurePolicy::Retry(retries) => write!(f, "retry:{}", retries),
+            FailurePolicy::Remove => f.write_str("remove"),
+            FailurePolicy::Abort => f.write_str("abort"),
+        }
+    }
+}
+
+/// A condition which ends the game once it is met
+#[derive(Clone)]
+pub enum EndCondition {
+    /// This many steps have been played
+    StepLimit(u64),
+    /// This much time has passed since the first step
+    WallClock(Duration),
+    /// This many tags have been made
+    TagCount(u64),
+    /// The game has been won according to the rules, such as everyone being infected or frozen
+    Won,
+    /// A custom predicate over the simulation returns true
+    Custom(Arc<dyn Fn(&Simulation) -> bool + Send + Sync>),
+}
+
+impl EndCondition {
+    fn is_met(&self, simulation: &Simulation) -> bool {
+        match self {
+            EndCondition::StepLimit(steps) => simulation.environment.step() >= *steps,
+            EndCondition::WallClock(duration) => simulation
+                .started_at
+                .is_some_and(|started_at| started_at.elapsed() >= *duration),
+            EndCondition::TagCount(tags) => simulation.statistics.tags() >= *tags,
+            EndCondition::Won => simulation.environment.is_game_over(),
+            EndCondition::Custom(predicate) => predicate(simulation),
+        }
+    }
+
+    fn reason(&self) -> EndReason {
+        match self {
+            EndCondition::StepLimit(_) => EndReason::StepLimit,
+            EndCondition::WallClock(_) => EndReason::WallClock,
+            EndCondition::TagCount(_) => EndReason::TagCount,
+            EndCondition::Won => EndReason::Won,
+            EndCondition::Custom(_) => EndReason::Custom,
+        }
+    }
+}
+
+impl fmt::Debug for EndCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StepLimit(steps) => f.debug_tuple("StepLimit").field(steps).finish(),
+            Self::WallClock(duration) => f.debug_tuple("WallClock").field(duration).finish(),
+            Self::TagCount(tags) => f.debug_tuple("TagCount").field(tags).finish(),
+            Self::Won => f.write_str("Won"),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Which kind of end condition ended the game
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EndReason {
+    StepLimit,
+    WallClock,
+    TagCount,
+    Won,
+    Custom,
+}
+
+/// Whether the game is still going after a step
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StepStatus {
+    Running,
+    Ended(EndReason),
+}
+
+/// Summary of how a game played out
+#[derive(Clone, Debug)]
+pub struct GameOutcome {
+    /// The rule set that was played
+    pub mode: GameMode,
+    /// How many steps were played
+    pub steps: u64,
+    /// Why the game ended, if it has
+    pub end_reason: Option<EndReason>,
+    /// The step on which the game was won, for infection this is the time to full infection
+    pub won_at: Option<u64>,
+    /// How each player played, including their score
+    pub statistics: Statistics,
+    /// The players who ended the game as it
+    pub it: Vec<PlayerId>,
+    /// Captures made by each team in team tag
+    pub team_scores: Vec<u32>,
+    /// Failures of each player's agent, players after the last one whose agent failed are left out
+    pub agent_failures: Vec<AgentFailures>,
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.mode, self.won_at) {
+            (GameMode::Freeze, Some(step)) => {
+                writeln!(f, "Everyone was frozen after {} steps", step)?
+            }
+            (GameMode::Infection, Some(step)) => {
+                writeln!(f, "Everyone was infected after {} steps", step)?
+            }
+            (GameMode::Teams, Some(step)) => {
+                writeln!(f, "Only one team was left free after {} steps", step)?
+            }
+            _ => match self.end_reason {
+                Some(EndReason::WallClock) => {
+                    writeln!(f, "Time ran out after {} steps", self.steps)?
+                }
+                Some(EndReason::TagCount) => {
+                    writeln!(f, "Tag limit was reached after {} steps", self.steps)?
+                }
+                Some(_) => writeln!(f, "Game stopped after {} steps", self.steps)?,
+                None => writeln!(f, "Game still running after {} steps", self.steps)?,
+            },
+        }
+        writeln!(f, "{} tags were made", self.statistics.tags())?;
+        for (team, score) in self.team_scores.iter().enumerate() {
+            if self.mode == GameMode::Teams {
+                writeln!(f, "Team {}: {} captures", team, score)?;
+            }
+        }
+        if !self.it.is_empty() {
+            let it: Vec<_> = self.it.iter().map(PlayerId::to_string).collect();
+            writeln!(f, "Ended as it: {}", it.join(", "))?;
+        }
+        for (player_id, failures) in self.agent_failures.iter().enumerate() {
+            if failures.count > 0 {
+                writeln!(
+                    f,
+                    "Player {}'s agent failed {} times, last because: {}",
+                    player_id, failures.count, failures.last_error
+                )?;
+            }
+        }
+        write!(f, "{}", self.statistics)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::agent::TagPlayerAgent;
+
+    fn simulation(mode: GameMode) -> Simulation {
+        Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            Rules::new(mode),
+            [(0., 0.), (50., 50.), (0.5, 0.)]
+                .into_iter()
+                .enumerate()
+                .map(|(i, position)| {
+                    let status = if i == 0 {
+                        TagStatus::It {
+                            tagged_by: 0,
+                            tagged_at: 0,
+                        }
+                    } else {
+                        TagStatus::NotIt
+                    };
+                    let state = TagPlayerVisibleState {
+                        position: position.into(),
+                        status,
+                        team: 0,
+                    };
+                    (Box::new(TagPlayerAgent::default()) as Box<dyn Agent>, state)
+                }),
+        )
+    }
+
+    #[test]
+    fn ends_at_step_limit() {
+        let mut simulation = simulation(GameMode::Classic).end_when(EndCondition::StepLimit(3));
+        assert_eq!(simulation.step().unwrap(), StepStatus::Running);
+        assert_eq!(simulation.step().unwrap(), StepStatus::Running);
+        assert_eq!(
+            simulation.step().unwrap(),
+            StepStatus::Ended(EndReason::StepLimit)
+        );
+        assert_eq!(
+            simulation.step().unwrap(),
+            StepStatus::Ended(EndReason::StepLimit)
+        );
+        assert_eq!(simulation.outcome().steps, 3);
+    }
+
+    #[test]
+    fn ends_at_tag_count() {
+        let mut simulation = simulation(GameMode::Classic)
+            .end_when(EndCondition::TagCount(1))
+            .end_when(EndCondition::StepLimit(10));
+        assert_eq!(
+            simulation.step().unwrap(),
+            StepStatus::Ended(EndReason::TagCount)
+        );
+        let outcome = simulation.outcome();
+        assert_eq!(outcome.it, vec![2]);
+        let players = outcome.statistics.players();
+        let first_caught_at: Vec<_> = players.iter().map(|p| p.first_tagged_at).collect();
+        assert_eq!(first_caught_at, vec![None, None, Some(1)]);
+        let scores: Vec<_> = players.iter().map(|p| p.steps_free).collect();
+        assert_eq!(scores, vec![1, 1, 0]);
+        assert_eq!(players[0].tags_made, 1);
+    }
+
+    #[test]
+    fn ends_with_custom_condition() {
+        let mut simulation = simulation(GameMode::Infection).end_when(EndCondition::Custom(
+            Arc::new(|simulation: &Simulation| simulation.environment().get_its().count() > 1),
+        ));
+        assert_eq!(
+            simulation.step().unwrap(),
+            StepStatus::Ended(EndReason::Custom)
+        );
+        assert_eq!(simulation.outcome().it, vec![0, 2]);
+    }
+
+    /// Tags without being it, which the referee doesn't allow
+    #[derive(Debug)]
+    struct CheatingAgent;
+
+    impl Agent for CheatingAgent {
+        fn act(&mut self, _: PlayerId, _: &TagEnvironment) -> Result<TagPlayerAction> {
+            Ok(TagPlayerAction::Tag { player_id: 0 })
+        }
+    }
+
+    #[test]
+    fn skips_failed_turns() {
+        let mut simulation = simulation(GameMode::Classic).end_when(EndCondition::StepLimit(2));
+        simulation.agents[1] = Some(Box::new(CheatingAgent));
+        simulation.step().unwrap();
+        assert_eq!(
+            simulation.actions()[1],
+            TagPlayerAction::Run {
+                stretch: Vector2D::zero()
+            }
+        );
+        simulation.step().unwrap();
+        let outcome = simulation.outcome();
+        assert_eq!(outcome.steps, 2);
+        assert_eq!(outcome.agent_failures.len(), 2);
+        assert_eq!(outcome.agent_failures[0].count, 0);
+        assert_eq!(outcome.agent_failures[1].count, 2);
+        assert!(outcome.agent_failures[1].last_error.contains("not it"));
+        assert_eq!(
+            simulation.events(),
+            &[GameEvent::AgentFailed { player: 1 }][..]
+        );
+    }
+
+    /// Fails the first time it's asked, then stands still
+    #[derive(Debug, Default)]
+    struct FlakyAgent {
+        failed: bool,
+    }
+
+    impl Agent for FlakyAgent {
+        fn act(&mut self, _: PlayerId, _: &TagEnvironment) -> Result<TagPlayerAction> {
+            if std::mem::replace(&mut self.failed, true) {
+                Ok(STAND_STILL)
+            } else {
+                Err("flaked".into())
+            }
+        }
+    }
+
+    #[test]
+    fn failure_policies() {
+        let mut retrying = simulation(GameMode::Classic).on_failure(FailurePolicy::Retry(1));
+        retrying.agents[1] = Some(Box::new(FlakyAgent::default()));
+        retrying.step().unwrap();
+        assert!(retrying.outcome().agent_failures.is_empty());
+
+        let mut removing = simulation(GameMode::Infection).on_failure(FailurePolicy::Remove);
+        removing.agents[2] = Some(Box::new(CheatingAgent));
+        removing.step().unwrap();
+        assert!(removing
+            .events()
+            .contains(&GameEvent::Removed { player: 2 }));
+        assert!(!removing.environment().is_playing(2));
+        assert_eq!(removing.environment().players().count(), 2);
+        removing.step().unwrap();
+        assert_eq!(removing.outcome().agent_failures[2].count, 1);
+
+        let mut aborting = simulation(GameMode::Classic).on_failure(FailurePolicy::Abort);
+        aborting.agents[1] = Some(Box::new(CheatingAgent));
+        assert!(aborting.step().is_err());
+        assert_eq!(aborting.environment().step(), 0);
+
+        assert_eq!("retry:3".parse(), Ok(FailurePolicy::Retry(3)));
+        assert_eq!(
+            FailurePolicy::Remove.to_string().parse(),
+            Ok(FailurePolicy::Remove)
+        );
+        assert!("retry".parse::<FailurePolicy>().is_err());
+    }
+
+    #[test]
+    fn players_join_and_leave() {
+        let mut simulation = simulation(GameMode::Classic);
+        let newcomer = simulation.add_player(
+            Box::new(TagPlayerAgent::default()),
+            TagPlayerVisibleState {
+                position: (90., 90.).into(),
+                status: TagStatus::NotIt,
+                team: 0,
+            },
+        );
+        assert_eq!(newcomer, 3);
+        simulation.remove_player(0);
+        simulation.remove_player(0);
+        assert_eq!(simulation.environment().get_its().count(), 1);
+        assert!(simulation.player_state()[2].is_it());
+        simulation.step().unwrap();
+        assert_eq!(simulation.actions().len(), 4);
+        assert_eq!(
+            simulation.events()[..3],
+            [
+                GameEvent::Joined { player: 3 },
+                GameEvent::Removed { player: 0 },
+                GameEvent::Promoted { player: 2 },
+            ]
+        );
+        assert_eq!(simulation.outcome().statistics.players().len(), 4);
+
+        let mut rules = Rules::new(GameMode::Freeze);
+        rules.succession = Succession::Random;
+        let mut simulation = Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            rules,
+            (0..10).map(|i| {
+                let state = TagPlayerVisibleState {
+                    position: (i as f32 * 10., 0.).into(),
+                    status: if i == 0 {
+                        TagStatus::It {
+                            tagged_by: 0,
+                            tagged_at: 0,
+                        }
+                    } else {
+                        TagStatus::NotIt
+                    },
+                    team: 0,
+                };
+                (Box::new(TagPlayerAgent::default()) as Box<dyn Agent>, state)
+            }),
+        )
+        .seed(2);
+        simulation.remove_player(0);
+        let (successor, _) = simulation.environment().get_its().next().unwrap();
+        assert_ne!(successor, 0);
+    }
 }
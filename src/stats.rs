@@ -0,0 +1,221 @@
+use std::fmt;
+
+use crate::environment::*;
+
+/// Statistics about how each player has played, recorded after each step of the simulation
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    players: Vec<PlayerStats>,
+    /// Where each player was after the previous step, to measure how far they have run
+    previous_positions: Vec<Position>,
+    /// How many tags have been made
+    tags: u64,
+    /// The step on which the last tag was made
+    last_tag_at: Option<u64>,
+}
+
+/// Statistics about how one player has played
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerStats {
+    /// Steps spent as it
+    pub steps_it: u64,
+    /// Steps spent neither it nor frozen, the player's score
+    pub steps_free: u64,
+    /// Tags this player has made
+    pub tags_made: u64,
+    /// Times this player has been tagged
+    pub times_tagged: u64,
+    /// The step on which the player was first tagged
+    pub first_tagged_at: Option<u64>,
+    /// How far the player has run
+    pub distance_run: PlayerDistance,
+    /// The most steps in a row the player has spent neither it nor frozen
+    pub longest_free_streak: u64,
+    /// Steps in a row the player has currently spent neither it nor frozen
+    pub current_free_streak: u64,
+    /// Sum of the distance to the closest chaser over the steps spent free
+    total_chaser_distance: PlayerDistance,
+    /// Number of steps included in `total_chaser_distance`
+    chaser_distance_samples: u64,
+}
+
+impl PlayerStats {
+    /// The mean distance to the closest chaser while the player was free, if there ever was a chaser
+    pub fn mean_distance_from_chaser(&self) -> Option<PlayerDistance> {
+        (self.chaser_distance_samples > 0)
+            .then(|| self.total_chaser_distance / self.chaser_distance_samples as PlayerDistance)
+    }
+}
+
+impl Statistics {
+    /// Start recording statistics for the players in the environment
+    pub fn new(environment: &TagEnvironment) -> Self {
+        Self {
+            players: vec![PlayerStats::default(); environment.player_state().len()],
+            previous_positions: environment
+                .player_state()
+                .iter()
+                .map(|s| s.position)
+                .collect(),
+            tags: 0,
+            last_tag_at: None,
+        }
+    }
+
+    /// Start recording statistics for a player who has joined the game
+    pub fn add_player(&mut self, state: &TagPlayerVisibleState) {
+        self.players.push(PlayerStats::default());
+        self.previous_positions.push(state.position);
+    }
+
+    /// Record what happened in the step which has just been applied to the environment
+    pub fn record(&mut self, environment: &TagEnvironment) {
+        let step = environment.step();
+        for event in environment.events() {
+            if let GameEvent::Tagged { by, player } = *event {
+                self.tags += 1;
+                self.last_tag_at = Some(step);
+                self.players[by].tags_made += 1;
+                let tagged = &mut self.players[player];
+                tagged.times_tagged += 1;
+                tagged.first_tagged_at.get_or_insert(step);
+            }
+        }
+        for (player_id, state) in environment.players() {
+            let stats = &mut self.players[player_id];
+            let previous_position = &mut self.previous_positions[player_id];
+            stats.distance_run += (state.position - *previous_position).length();
+            *previous_position = state.position;
+
+            if state.is_it() {
+                stats.steps_it += 1;
+            }
+            if state.is_it() || state.is_frozen() {
+                stats.current_free_streak = 0;
+                continue;
+            }
+            stats.steps_free += 1;
+            stats.current_free_streak += 1;
+            stats.longest_free_streak = stats.longest_free_streak.max(stats.current_free_streak);
+            if let Some((_, sq_distance)) =
+                environment.closest_player_where(player_id, |_, other| other.is_it())
+            {
+                stats.total_chaser_distance += sq_distance.sqrt();
+                stats.chaser_distance_samples += 1;
+            }
+        }
+    }
+
+    /// Statistics for each player
+    pub fn players(&self) -> &[PlayerStats] {
+        &self.players
+    }
+
+    /// How many tags have been made
+    pub fn tags(&self) -> u64 {
+        self.tags
+    }
+
+    /// The step on which the last tag was made
+    pub fn last_tag_at(&self) -> Option<u64> {
+        self.last_tag_at
+    }
+}
+
+/// A table summarising the statistics of all the players
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6} {:>6} {:>6} {:>5} {:>6} {:>8} {:>7} {:>7} {:>8}",
+            "player", "score", "it", "tags", "tagged", "distance", "streak", "chaser", "caught"
+        )?;
+        for (player_id, stats) in self.players().iter().enumerate() {
+            write!(
+                f,
+                "{:>6} {:>6} {:>6} {:>5} {:>6} {:>8.1} {:>7}",
+                player_id,
+                stats.steps_free,
+                stats.steps_it,
+                stats.tags_made,
+                stats.times_tagged,
+                stats.distance_run,
+                stats.longest_free_streak,
+            )?;
+            match stats.mean_distance_from_chaser() {
+                Some(distance) => write!(f, " {:>7.1}", distance)?,
+                None => write!(f, " {:>7}", "-")?,
+            }
+            match stats.first_tagged_at {
+                Some(step) => writeln!(f, " {:>8}", step)?,
+                None => writeln!(f, " {:>8}", "-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn record_steps() {
+        let mut environment = TagEnvironment::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            Rules::default(),
+            vec![
+                TagPlayerVisibleState {
+                    position: (0., 0.).into(),
+                    status: TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    },
+                    team: 0,
+                },
+                TagPlayerVisibleState {
+                    position: (0., 3.).into(),
+                    status: TagStatus::NotIt,
+                    team: 0,
+                },
+            ],
+        );
+        let mut statistics = Statistics::new(&environment);
+        environment.apply_actions(&[
+            TagPlayerAction::Run {
+                stretch: (0., 2.).into(),
+            },
+            TagPlayerAction::Run {
+                stretch: (0., 0.).into(),
+            },
+        ]);
+        statistics.record(&environment);
+        environment.apply_actions(&[
+            TagPlayerAction::Tag { player_id: 1 },
+            TagPlayerAction::Run {
+                stretch: (0., 0.).into(),
+            },
+        ]);
+        statistics.record(&environment);
+
+        assert_eq!(statistics.tags(), 1);
+        assert_eq!(statistics.last_tag_at(), Some(2));
+        let [chaser, runner] = statistics.players() else {
+            panic!("expected two players")
+        };
+        assert_eq!(chaser.steps_it, 1);
+        assert_eq!(chaser.steps_free, 1);
+        assert_eq!(chaser.tags_made, 1);
+        assert_eq!(chaser.distance_run, 2.);
+        assert_eq!(chaser.mean_distance_from_chaser(), Some(1.));
+        assert_eq!(runner.steps_it, 1);
+        assert_eq!(runner.times_tagged, 1);
+        assert_eq!(runner.first_tagged_at, Some(2));
+        assert_eq!(runner.longest_free_streak, 1);
+        assert_eq!(runner.current_free_streak, 0);
+        assert_eq!(runner.mean_distance_from_chaser(), Some(1.));
+    }
+}
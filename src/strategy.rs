@@ -0,0 +1,180 @@
+use std::{fmt, fs, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{
+    agent::{Agent, AgentParams, NaiveAgent, TagPlayerAgent},
+    environment::{self, PlayerId, TagEnvironment, TagPlayerAction},
+    mlp::{Mlp, MlpAgent},
+    process::ProcessAgent,
+    script::ScriptAgent,
+    wasm::WasmAgent,
+};
+
+/// Names an agent implementation, so that it can be chosen on the command line and an agent created for each player
+/// who uses it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Strategy {
+    /// [`TagPlayerAgent`]
+    Default,
+    /// [`NaiveAgent`]
+    Naive,
+    /// [`TagPlayerAgent`] with parameters loaded from a file, such as a tuned genome
+    Genome { path: String, params: AgentParams },
+    /// [`MlpAgent`] with a network loaded from a weights file
+    Network { path: String, network: Arc<Mlp> },
+    /// [`ProcessAgent`] running a command, which has to answer each step within the timeout
+    Process { command: String, timeout: Duration },
+    /// [`ScriptAgent`] running a Rhai script, which is reloaded when it changes
+    Script { path: String },
+    /// [`WasmAgent`] running a sandboxed WebAssembly module
+    Wasm { path: String, module: Arc<Vec<u8>> },
+}
+
+/// How long an external process has to answer each step, unless it is set on the command line
+pub const DEFAULT_PROCESS_TIMEOUT: Duration = Duration::from_millis(100);
+
+impl Strategy {
+    /// Create an agent to play a player with this strategy. If it can't be created, the agent fails every turn with
+    /// the reason, so the game's failure policy decides what happens
+    pub fn agent(&self) -> Box<dyn Agent> {
+        match self {
+            Strategy::Default => Box::new(TagPlayerAgent::default()),
+            Strategy::Naive => Box::new(NaiveAgent),
+            Strategy::Genome { params, .. } => Box::new(TagPlayerAgent { params: *params }),
+            Strategy::Network { network, .. } => Box::new(MlpAgent {
+                network: network.clone(),
+            }),
+            Strategy::Process { command, timeout } => {
+                match ProcessAgent::spawn(command, *timeout) {
+                    Ok(agent) => Box::new(agent),
+                    Err(error) => unavailable(format!("Failed to start '{}': {}", command, error)),
+                }
+            }
+            Strategy::Script { path } => match ScriptAgent::load(path) {
+                Ok(agent) => Box::new(agent),
+                Err(error) => unavailable(error.to_string()),
+            },
+            Strategy::Wasm { path, module } => match WasmAgent::load(module) {
+                Ok(agent) => Box::new(agent),
+                Err(error) => unavailable(format!("Invalid module '{}': {}", path, error)),
+            },
+        }
+    }
+}
+
+fn unavailable(reason: String) -> Box<dyn Agent> {
+    Box::new(UnavailableAgent { reason })
+}
+
+/// Stands in for an agent which couldn't be created, failing every turn
+#[derive(Debug)]
+struct UnavailableAgent {
+    reason: String,
+}
+
+impl Agent for UnavailableAgent {
+    fn act(&mut self, _: PlayerId, _: &TagEnvironment) -> environment::Result<TagPlayerAction> {
+        Err(self.reason.clone().into())
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Strategy::Default),
+            "naive" => Ok(Strategy::Naive),
+            _ => match s.split_once(':') {
+                Some(("genome", path)) => {
+                    let params = fs::read_to_string(path)
+                        .map_err(|error| format!("Failed to read genome '{}': {}", path, error))?
+                        .parse()
+                        .map_err(|error| format!("Invalid genome '{}': {}", path, error))?;
+                    Ok(Strategy::Genome {
+                        path: path.to_owned(),
+                        params,
+                    })
+                }
+                Some(("mlp", path)) => {
+                    let network = fs::read_to_string(path)
+                        .map_err(|error| format!("Failed to read network '{}': {}", path, error))?
+                        .parse()
+                        .map_err(|error| format!("Invalid network '{}': {}", path, error))?;
+                    // check the network fits once here, so that creating each agent can't fail
+                    let MlpAgent { network } = MlpAgent::new(Arc::new(network))
+                        .map_err(|error| format!("Invalid network '{}': {}", path, error))?;
+                    Ok(Strategy::Network {
+                        path: path.to_owned(),
+                        network,
+                    })
+                }
+                Some(("process", command)) if !command.trim().is_empty() => Ok(Strategy::Process {
+                    command: command.to_owned(),
+                    timeout: DEFAULT_PROCESS_TIMEOUT,
+                }),
+                Some(("script", path)) => {
+                    // check the script loads once here, so that a mistake is reported straight away
+                    ScriptAgent::load(path).map_err(|error| error.to_string())?;
+                    Ok(Strategy::Script {
+                        path: path.to_owned(),
+                    })
+                }
+                Some(("wasm", path)) => {
+                    let module = fs::read(path)
+                        .map_err(|error| format!("Failed to read module '{}': {}", path, error))?;
+                    // check the module loads once here, so creating each agent only fails if it's nondeterministic
+                    WasmAgent::load(&module)
+                        .map_err(|error| format!("Invalid module '{}': {}", path, error))?;
+                    Ok(Strategy::Wasm {
+                        path: path.to_owned(),
+                        module: Arc::new(module),
+                    })
+                }
+                _ => Err(format!("Unknown strategy '{}'", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Default => f.write_str("default"),
+            Strategy::Naive => f.write_str("naive"),
+            Strategy::Genome { path, .. } => write!(f, "genome:{}", path),
+            Strategy::Network { path, .. } => write!(f, "mlp:{}", path),
+            Strategy::Process { command, .. } => write!(f, "process:{}", command),
+            Strategy::Script { path } => write!(f, "script:{}", path),
+            Strategy::Wasm { path, .. } => write!(f, "wasm:{}", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::environment::*;
+
+    #[test]
+    fn parse_strategy() {
+        let process = Strategy::Process {
+            command: String::from("python3 bot.py"),
+            timeout: DEFAULT_PROCESS_TIMEOUT,
+        };
+        for strategy in [Strategy::Default, Strategy::Naive, process] {
+            assert_eq!(strategy.to_string().parse(), Ok(strategy));
+        }
+        assert!("clever".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn agents_which_cant_be_created_fail() {
+        let environment = test_environment((10., 10.), &[(1., 1.)]);
+        // the script was there when the strategy was chosen, but has gone since
+        let strategy = Strategy::Script {
+            path: String::from("no-such-script.rhai"),
+        };
+        let error = strategy.agent().act(0, &environment).unwrap_err();
+        assert!(error.to_string().contains("no-such-script.rhai"));
+    }
+}
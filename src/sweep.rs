@@ -0,0 +1,306 @@
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use euclid::default::{Point2D, Rect};
+use rayon::prelude::*;
+
+use crate::{
+    environment::PlayerDistance, scenario::Scenario, simulation::StepStatus, strategy::Strategy,
+};
+
+/// Header of the results table
+const HEADER: &str =
+    "arena,players,speed,reach,strategy,seed,steps,tags,catch_rate,won_at,mean_score";
+/// How many of the columns identify a run, the parameters and the seed
+const KEY_COLUMNS: usize = 6;
+
+/// Runs every combination of the parameters with several seeds each
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    /// The scenario to vary, the parameters swept override its settings
+    pub base: Scenario,
+    /// Widths and heights of the square play area
+    pub arena_sizes: Vec<f32>,
+    pub player_counts: Vec<usize>,
+    pub speeds: Vec<PlayerDistance>,
+    pub reaches: Vec<PlayerDistance>,
+    /// Every player uses the same strategy in each run
+    pub strategies: Vec<Strategy>,
+    /// Each combination is run with each seed from 0 up to this
+    pub seeds: u64,
+}
+
+/// One run of the sweep
+#[derive(Clone, Debug)]
+struct SweepRun<'a> {
+    arena_size: f32,
+    player_count: usize,
+    speed: PlayerDistance,
+    reach: PlayerDistance,
+    strategy: &'a Strategy,
+    seed: u64,
+}
+
+impl Sweep {
+    /// Play every run which isn't already in the results file, appending a row to it as each run finishes so that
+    /// an interrupted sweep can be resumed. Returns how many runs were played
+    pub fn run(&self, path: &Path) -> io::Result<usize> {
+        let completed = completed_runs(path)?;
+        let mut out = OpenOptions::new().create(true).append(true).open(path)?;
+        if out.metadata()?.len() == 0 {
+            writeln!(out, "{}", HEADER)?;
+        }
+        let out = Mutex::new(out);
+
+        let runs: Vec<_> = self
+            .runs()
+            .filter(|run| !completed.contains(&run.key()))
+            .collect();
+        runs.par_iter().try_for_each(|run| {
+            let row = format!("{},{}\n", run.key(), self.play(run));
+            out.lock().unwrap().write_all(row.as_bytes())
+        })?;
+        Ok(runs.len())
+    }
+
+    /// Every combination of the parameters with every seed
+    fn runs(&self) -> impl Iterator<Item = SweepRun<'_>> + '_ {
+        self.arena_sizes.iter().flat_map(move |&arena_size| {
+            self.player_counts.iter().flat_map(move |&player_count| {
+                self.speeds.iter().flat_map(move |&speed| {
+                    self.reaches.iter().flat_map(move |&reach| {
+                        self.strategies.iter().flat_map(move |strategy| {
+                            (0..self.seeds).map(move |seed| SweepRun {
+                                arena_size,
+                                player_count,
+                                speed,
+                                reach,
+                                strategy,
+                                seed,
+                            })
+                        })
+                    })
+                })
+            })
+        })
+    }
+
+    /// Play a run and return the result columns of its row
+    fn play(&self, run: &SweepRun) -> String {
+        let mut scenario = self.base.clone();
+        scenario.area = Rect::from_points([
+            Point2D::new(0., 0.),
+            Point2D::new(run.arena_size, run.arena_size),
+        ]);
+        scenario.player_count = run.player_count;
+        scenario.rules.max_speed = run.speed;
+        scenario.rules.tag_reach = run.reach;
+        let mut simulation = scenario.simulation(run.seed, |_| run.strategy.agent());
+        while simulation.step().expect("Failed agents skip their turns") == StepStatus::Running {}
+
+        let outcome = simulation.outcome();
+        let tags = outcome.statistics.tags();
+        let players = outcome.statistics.players();
+        let mean_score = players
+            .iter()
+            .map(|stats| stats.steps_free as f64)
+            .sum::<f64>()
+            / players.len() as f64;
+        format!(
+            "{},{},{},{},{}",
+            outcome.steps,
+            tags,
+            tags as f64 / outcome.steps as f64,
+            outcome
+                .won_at
+                .map_or(String::new(), |step| step.to_string()),
+            mean_score
+        )
+    }
+}
+
+impl SweepRun<'_> {
+    /// The columns of the row which identify the run
+    fn key(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.arena_size,
+            self.player_count,
+            self.speed,
+            self.reach,
+            csv_field(&self.strategy.to_string()),
+            self.seed
+        )
+    }
+}
+
+/// Quote a field if it contains a separator, a quote or a line break, doubling any quotes inside it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// The columns of a row which identify its run, as written by [`SweepRun::key`]
+fn row_key(row: &str) -> &str {
+    let mut quoted = false;
+    let mut columns = 0;
+    for (i, c) in row.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                columns += 1;
+                if columns == KEY_COLUMNS {
+                    return &row[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    row
+}
+
+/// The keys of the runs already in the results file. If the last row was only partly written it is removed
+fn completed_runs(path: &Path) -> io::Result<HashSet<String>> {
+    let mut contents = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut contents)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(error) => return Err(error),
+    };
+    let header_matches = match contents.split_once('\n') {
+        Some((header, _)) => header == HEADER,
+        None => HEADER.starts_with(&contents),
+    };
+    if !header_matches {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} isn't a sweep results file, its header doesn't match",
+                path.display()
+            ),
+        ));
+    }
+    let complete_len = contents.rfind('\n').map_or(0, |end| end + 1);
+    if complete_len < contents.len() {
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(complete_len as u64)?;
+    }
+    Ok(contents[..complete_len]
+        .lines()
+        .skip(1)
+        .map(|row| row_key(row).to_owned())
+        .collect())
+}
+
+/// Parse values to sweep over, either a list like `2,5,10` or an inclusive range with a step like `0.5..3:0.5`
+pub fn parse_values(values: &str) -> Result<Vec<f64>, String> {
+    let invalid = || format!("Invalid values to sweep '{}'", values);
+    if let Some((start, rest)) = values.split_once("..") {
+        let (end, step) = rest.split_once(':').unwrap_or((rest, "1"));
+        let parse = |value: &str| value.parse::<f64>().map_err(|_| invalid());
+        let (start, end, step) = (parse(start)?, parse(end)?, parse(step)?);
+        if step <= 0. || end < start {
+            return Err(invalid());
+        }
+        // allow for rounding errors so that the end is included
+        let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+        Ok((0..count).map(|i| start + step * i as f64).collect())
+    } else {
+        values
+            .split(',')
+            .map(|value| value.parse().map_err(|_| invalid()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::{
+        rules::{GameMode, Rules},
+        strategy::DEFAULT_PROCESS_TIMEOUT,
+    };
+
+    #[test]
+    fn parse_sweep_values() {
+        assert_eq!(parse_values("2,5,10"), Ok(vec![2., 5., 10.]));
+        assert_eq!(parse_values("0.5..2:0.5"), Ok(vec![0.5, 1., 1.5, 2.]));
+        assert_eq!(parse_values("2..4"), Ok(vec![2., 3., 4.]));
+        assert!(parse_values("4..2").is_err());
+        assert!(parse_values("far").is_err());
+    }
+
+    #[test]
+    fn resume_sweep() {
+        let path = env::temp_dir().join(format!("tag-sweep-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let sweep = Sweep {
+            base: Scenario {
+                area: Rect::from_points([Point2D::new(0., 0.), Point2D::new(100., 100.)]),
+                rules: Rules::new(GameMode::Infection),
+                player_count: 5,
+                team_count: 2,
+                step_limit: 50,
+                end_conditions: Vec::new(),
+            },
+            arena_sizes: vec![20., 50.],
+            player_counts: vec![3],
+            speeds: vec![2.],
+            reaches: vec![1., 2.5],
+            strategies: vec![Strategy::Default],
+            seeds: 2,
+        };
+        assert_eq!(sweep.run(&path).unwrap(), 8);
+
+        // interrupted while writing the last row
+        let contents = fs::read_to_string(&path).unwrap();
+        let last_row_start = contents.trim_end().rfind('\n').unwrap() + 1;
+        fs::write(&path, &contents[..last_row_start + 5]).unwrap();
+        assert_eq!(sweep.run(&path).unwrap(), 1);
+        assert_eq!(sweep.run(&path).unwrap(), 0);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let rows: Vec<_> = contents.lines().collect();
+        assert_eq!(rows[0], HEADER);
+        assert_eq!(rows.len(), 9);
+        assert!(rows[1..].iter().all(|row| row.split(',').count() == 11));
+
+        fs::write(&path, "arena,players\n20,3\n").unwrap();
+        assert_eq!(
+            sweep.run(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn quoted_strategy_key() {
+        let strategy = Strategy::Process {
+            command: String::from("python3 bot.py --name \"a,b\""),
+            timeout: DEFAULT_PROCESS_TIMEOUT,
+        };
+        let run = SweepRun {
+            arena_size: 20.,
+            player_count: 3,
+            speed: 2.,
+            reach: 1.,
+            strategy: &strategy,
+            seed: 4,
+        };
+        let key = run.key();
+        assert_eq!(key, r#"20,3,2,1,"process:python3 bot.py --name ""a,b""",4"#);
+        assert_eq!(row_key(&format!("{},50,3,0.06,,1.5", key)), key);
+    }
+}
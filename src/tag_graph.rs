@@ -0,0 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Write},
+};
+
+use crate::environment::*;
+
+/// The full history of who tagged whom, a directed multigraph with an edge for each tag
+#[derive(Debug, Clone)]
+pub struct TagGraph {
+    player_count: usize,
+    edges: Vec<TagEdge>,
+}
+
+/// One tag, from the tagger to the tagged player
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TagEdge {
+    pub by: PlayerId,
+    pub player: PlayerId,
+    /// The step during which the tag was made
+    pub step: u64,
+}
+
+impl TagGraph {
+    pub fn new(player_count: usize) -> Self {
+        Self {
+            player_count,
+            edges: Vec::new(),
+        }
+    }
+
+    /// Make room for a player who has joined the game
+    pub fn add_player(&mut self) {
+        self.player_count += 1;
+    }
+
+    /// Record the tags made in the step which has just been applied to the environment
+    pub fn record(&mut self, environment: &TagEnvironment) {
+        let step = environment.step();
+        for event in environment.events() {
+            if let GameEvent::Tagged { by, player } = *event {
+                self.edges.push(TagEdge { by, player, step });
+            }
+        }
+    }
+
+    /// How many tags the player has made
+    pub fn out_degree(&self, player_id: PlayerId) -> usize {
+        self.edges
+            .iter()
+            .filter(|edge| edge.by == player_id)
+            .count()
+    }
+
+    /// How many times the player has been tagged
+    pub fn in_degree(&self, player_id: PlayerId) -> usize {
+        self.edges
+            .iter()
+            .filter(|edge| edge.player == player_id)
+            .count()
+    }
+
+    /// How many times each tagger has tagged each player, only including pairs where it happened more than once
+    pub fn repeated_pairs(&self) -> BTreeMap<(PlayerId, PlayerId), usize> {
+        let mut pairs = BTreeMap::new();
+        for edge in &self.edges {
+            *pairs.entry((edge.by, edge.player)).or_insert(0) += 1;
+        }
+        pairs.retain(|_, count| *count > 1);
+        pairs
+    }
+
+    /// Tags which were straight back at the player who last tagged the tagger, counted for each pair of players
+    /// (lowest id first). Lots of these between two players is tag ping-pong, where the rest of the players are left
+    /// out of the game.
+    pub fn tag_backs(&self) -> BTreeMap<(PlayerId, PlayerId), usize> {
+        let mut last_tagged_by = vec![None; self.player_count];
+        let mut tag_backs = BTreeMap::new();
+        for edge in &self.edges {
+            if last_tagged_by[edge.by] == Some(edge.player) {
+                let pair = (edge.by.min(edge.player), edge.by.max(edge.player));
+                *tag_backs.entry(pair).or_insert(0) += 1;
+            }
+            last_tagged_by[edge.player] = Some(edge.by);
+        }
+        tag_backs
+    }
+
+    /// Write the graph in Graphviz DOT format
+    pub fn write_dot(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph tags {{")?;
+        for player_id in 0..self.player_count {
+            writeln!(out, "    {};", player_id)?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                edge.by, edge.player, edge.step
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Write the graph in GraphML format
+    pub fn write_graphml(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="step" for="edge" attr.name="step" attr.type="long"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="tags" edgedefault="directed">"#)?;
+        for player_id in 0..self.player_count {
+            writeln!(out, r#"    <node id="p{}"/>"#, player_id)?;
+        }
+        for (edge_id, edge) in self.edges.iter().enumerate() {
+            writeln!(
+                out,
+                r#"    <edge id="e{}" source="p{}" target="p{}"><data key="step">{}</data></edge>"#,
+                edge_id, edge.by, edge.player, edge.step
+            )?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+}
+
+/// A summary of the metrics of the graph
+impl fmt::Display for TagGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>6} {:>4} {:>4}", "player", "out", "in")?;
+        for player_id in 0..self.player_count {
+            writeln!(
+                f,
+                "{:>6} {:>4} {:>4}",
+                player_id,
+                self.out_degree(player_id),
+                self.in_degree(player_id)
+            )?;
+        }
+        for ((by, player), count) in self.repeated_pairs() {
+            writeln!(f, "Player {} tagged player {} {} times", by, player, count)?;
+        }
+        for ((a, b), count) in self.tag_backs() {
+            writeln!(
+                f,
+                "Players {} and {} tagged each other straight back {} times",
+                a, b, count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph(edges: &[(PlayerId, PlayerId)]) -> TagGraph {
+        TagGraph {
+            player_count: 3,
+            edges: edges
+                .iter()
+                .enumerate()
+                .map(|(step, &(by, player))| TagEdge {
+                    by,
+                    player,
+                    step: step as u64 + 1,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn detect_ping_pong() {
+        let graph = graph(&[(0, 1), (1, 0), (0, 1), (1, 2), (2, 0)]);
+        assert_eq!(graph.out_degree(0), 2);
+        assert_eq!(graph.in_degree(0), 2);
+        assert_eq!(graph.in_degree(2), 1);
+        assert_eq!(graph.repeated_pairs(), BTreeMap::from([((0, 1), 2)]));
+        assert_eq!(graph.tag_backs(), BTreeMap::from([((0, 1), 2)]));
+    }
+
+    #[test]
+    fn export_dot() {
+        let mut dot = Vec::new();
+        graph(&[(0, 1)]).write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "digraph tags {\n    0;\n    1;\n    2;\n    0 -> 1 [label=\"1\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use std::{collections::BTreeSet, fmt};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{rules::GameMode, scenario::Scenario, simulation::StepStatus, strategy::Strategy};
+
+/// Rating of the virtual opponent which anchors the ratings, so that an average strategy is rated around this
+const ANCHOR_RATING: f64 = 1500.;
+/// How many iterations are used to fit the ratings to the games
+const FIT_ITERATIONS: usize = 500;
+/// How many times the games are resampled to estimate the confidence intervals of the ratings
+const BOOTSTRAP_SAMPLES: usize = 1000;
+
+/// Plays strategies against each other across many seeds and rates them
+#[derive(Clone, Debug)]
+pub struct Tournament {
+    pub scenario: Scenario,
+    pub strategies: Vec<Strategy>,
+    /// Each pair of strategies plays a game with each seed from 0 up to this, and then again with sides swapped
+    pub seeds: u64,
+    pub pairing: Pairing,
+}
+
+/// How strategies are paired up to play each other
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pairing {
+    /// Every strategy plays every other strategy
+    RoundRobin,
+    /// For this many rounds, each strategy plays the strategy with the closest rating which it hasn't played yet
+    Swiss { rounds: usize },
+}
+
+/// The result of one game between two strategies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameResult {
+    /// Index of the first strategy
+    pub a: usize,
+    /// Index of the second strategy
+    pub b: usize,
+    pub seed: u64,
+    /// Whether the strategies swapped sides, so that `b` played the first player (who starts as it)
+    pub swapped: bool,
+    /// 1 if `a` won, 0.5 for a draw and 0 if `b` won
+    pub score: f64,
+}
+
+impl Tournament {
+    /// Play all the games and rate the strategies
+    pub fn run(&self) -> TournamentReport {
+        assert!(
+            self.strategies.len() > 1,
+            "A tournament needs at least 2 strategies"
+        );
+        assert!(
+            self.scenario.rules.mode != GameMode::Teams || self.scenario.team_count == 2,
+            "Strategies can only play each other in team tag with 2 teams"
+        );
+        let games = match self.pairing {
+            Pairing::RoundRobin => {
+                let pairs: Vec<_> = (0..self.strategies.len())
+                    .flat_map(|a| (a + 1..self.strategies.len()).map(move |b| (a, b)))
+                    .collect();
+                self.play(&pairs)
+            }
+            Pairing::Swiss { rounds } => {
+                let mut games = Vec::new();
+                let mut played = BTreeSet::new();
+                for _ in 0..rounds {
+                    let ratings = elo(self.strategies.len(), &games);
+                    let pairs = swiss_pairs(&ratings, &played);
+                    played.extend(pairs.iter().copied());
+                    games.extend(self.play(&pairs));
+                }
+                games
+            }
+        };
+        TournamentReport::new(self.strategies.clone(), games)
+    }
+
+    /// Play each pair of strategies with every seed on both sides, in parallel
+    fn play(&self, pairs: &[(usize, usize)]) -> Vec<GameResult> {
+        let games: Vec<_> = pairs
+            .iter()
+            .flat_map(|&(a, b)| {
+                (0..self.seeds).flat_map(move |seed| [(a, b, seed, false), (a, b, seed, true)])
+            })
+            .collect();
+        games
+            .into_par_iter()
+            .map(|(a, b, seed, swapped)| self.play_game(a, b, seed, swapped))
+            .collect()
+    }
+
+    /// Play a game with `a` on one side and `b` on the other. A side wins by having the higher mean score across its
+    /// players, or in team tag by its team making more captures
+    fn play_game(&self, a: usize, b: usize, seed: u64, swapped: bool) -> GameResult {
+        let sides = if swapped { [b, a] } else { [a, b] };
+        let mut simulation = self.scenario.simulation(seed, |player_id| {
+            self.strategies[sides[self.scenario.side(player_id)]].agent()
+        });
+        while simulation.step().expect("Failed agents skip their turns") == StepStatus::Running {}
+
+        let outcome = simulation.outcome();
+        let mut side_scores = [0.; 2];
+        if outcome.mode == GameMode::Teams {
+            for (side, score) in side_scores.iter_mut().enumerate() {
+                *score = outcome.team_scores[side] as f64;
+            }
+        } else {
+            let mut side_players = [0.; 2];
+            for (player_id, stats) in outcome.statistics.players().iter().enumerate() {
+                side_scores[self.scenario.side(player_id)] += stats.steps_free as f64;
+                side_players[self.scenario.side(player_id)] += 1.;
+            }
+            for (score, players) in side_scores.iter_mut().zip(side_players) {
+                *score /= players;
+            }
+        }
+        let [a_score, b_score] = if swapped {
+            [side_scores[1], side_scores[0]]
+        } else {
+            side_scores
+        };
+        let score = if a_score > b_score {
+            1.
+        } else if a_score < b_score {
+            0.
+        } else {
+            0.5
+        };
+        GameResult {
+            a,
+            b,
+            seed,
+            swapped,
+            score,
+        }
+    }
+}
+
+/// Pair up strategies with close ratings that haven't played each other yet, if a strategy has played all the others
+/// it plays the closest rated one again. With an odd number of strategies the lowest rated sits the round out
+fn swiss_pairs(ratings: &[f64], played: &BTreeSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut unpaired: Vec<usize> = (0..ratings.len()).collect();
+    unpaired.sort_by(|a, b| ratings[*b].total_cmp(&ratings[*a]));
+    let mut pairs = Vec::new();
+    while unpaired.len() > 1 {
+        let a = unpaired.remove(0);
+        let opponent = unpaired
+            .iter()
+            .position(|&b| !played.contains(&(a.min(b), a.max(b))))
+            .unwrap_or(0);
+        let b = unpaired.remove(opponent);
+        pairs.push((a.min(b), a.max(b)));
+    }
+    pairs
+}
+
+/// Ratings on the Elo scale which best fit the games, whatever order they were played in. This is the maximum
+/// likelihood Bradley-Terry model fitted with Hunter's MM algorithm. Each strategy also gets a virtual win and loss
+/// against an opponent rated [`ANCHOR_RATING`], which keeps the ratings finite when a strategy wins every game
+fn elo<'a>(strategy_count: usize, games: impl IntoIterator<Item = &'a GameResult>) -> Vec<f64> {
+    let mut wins = vec![1.; strategy_count];
+    let mut played = vec![vec![0.; strategy_count]; strategy_count];
+    for game in games {
+        wins[game.a] += game.score;
+        wins[game.b] += 1. - game.score;
+        played[game.a][game.b] += 1.;
+        played[game.b][game.a] += 1.;
+    }
+    let mut strengths = vec![1.; strategy_count];
+    for _ in 0..FIT_ITERATIONS {
+        strengths = (0..strategy_count)
+            .map(|a| {
+                let against_anchor = 2. / (strengths[a] + 1.);
+                let against_others: f64 = (0..strategy_count)
+                    .map(|b| played[a][b] / (strengths[a] + strengths[b]))
+                    .sum();
+                wins[a] / (against_anchor + against_others)
+            })
+            .collect();
+    }
+    strengths
+        .into_iter()
+        .map(|strength| ANCHOR_RATING + 400. * strength.log10())
+        .collect()
+}
+
+/// The results of a tournament
+#[derive(Clone, Debug)]
+pub struct TournamentReport {
+    pub strategies: Vec<Strategy>,
+    pub games: Vec<GameResult>,
+    pub ratings: Vec<Rating>,
+}
+
+/// How well a strategy did in a tournament
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    /// Lower bound of the 95% confidence interval of the Elo rating
+    pub low: f64,
+    /// Upper bound of the 95% confidence interval of the Elo rating
+    pub high: f64,
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+}
+
+impl TournamentReport {
+    /// Rate the strategies from the games, with confidence intervals from rating resamples of the games
+    fn new(strategies: Vec<Strategy>, games: Vec<GameResult>) -> Self {
+        let mut ratings: Vec<_> = elo(strategies.len(), &games)
+            .into_iter()
+            .map(|elo| Rating {
+                elo,
+                ..Default::default()
+            })
+            .collect();
+        for game in &games {
+            for (strategy, score) in [(game.a, game.score), (game.b, 1. - game.score)] {
+                let rating = &mut ratings[strategy];
+                match score {
+                    1. => rating.wins += 1,
+                    0. => rating.losses += 1,
+                    _ => rating.draws += 1,
+                }
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut samples = vec![Vec::with_capacity(BOOTSTRAP_SAMPLES); strategies.len()];
+        for _ in 0..BOOTSTRAP_SAMPLES {
+            let resampled = (0..games.len()).map(|_| &games[rng.gen_range(0..games.len())]);
+            for (strategy, elo) in elo(strategies.len(), resampled).into_iter().enumerate() {
+                samples[strategy].push(elo);
+            }
+        }
+        for (rating, mut samples) in ratings.iter_mut().zip(samples) {
+            samples.sort_by(f64::total_cmp);
+            rating.low = samples[BOOTSTRAP_SAMPLES * 25 / 1000];
+            rating.high = samples[BOOTSTRAP_SAMPLES * 975 / 1000 - 1];
+        }
+
+        Self {
+            strategies,
+            games,
+            ratings,
+        }
+    }
+}
+
+/// A table of the strategies, best rated first
+impl fmt::Display for TournamentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} games played", self.games.len())?;
+        let name_width = self
+            .strategies
+            .iter()
+            .map(|strategy| strategy.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max(8);
+        writeln!(
+            f,
+            "{:>4} {:<name_width$} {:>6} {:>15} {:>5} {:>5} {:>5}",
+            "rank", "strategy", "elo", "95% interval", "won", "drawn", "lost"
+        )?;
+        let mut ranking: Vec<_> = self.strategies.iter().zip(&self.ratings).collect();
+        ranking.sort_by(|(_, a), (_, b)| b.elo.total_cmp(&a.elo));
+        for (rank, (strategy, rating)) in ranking.into_iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4} {:<name_width$} {:>6.0} {:>15} {:>5} {:>5} {:>5}",
+                rank + 1,
+                strategy.to_string(),
+                rating.elo,
+                format!("{:.0} - {:.0}", rating.low, rating.high),
+                rating.wins,
+                rating.draws,
+                rating.losses
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::{environment::Position, rules::Rules};
+
+    #[test]
+    fn elo_ratings() {
+        let win = GameResult {
+            a: 0,
+            b: 1,
+            seed: 0,
+            swapped: false,
+            score: 1.,
+        };
+        let ratings = elo(3, &[win]);
+        assert!(ratings[0] > ANCHOR_RATING && ratings[1] < ANCHOR_RATING);
+        assert!((ratings[0] - ANCHOR_RATING - (ANCHOR_RATING - ratings[1])).abs() < 1e-6);
+        assert!((ratings[2] - ANCHOR_RATING).abs() < 1e-6);
+        // the order of the games doesn't matter
+        let loss = GameResult { score: 0., ..win };
+        assert_eq!(elo(2, &[win, win, loss]), elo(2, &[loss, win, win]));
+        let draw = GameResult { score: 0.5, ..win };
+        for rating in elo(2, &[draw, draw]) {
+            assert!((rating - ANCHOR_RATING).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn swiss_pairing_avoids_rematches() {
+        let ratings = [1500., 1520., 1480., 1510.];
+        assert_eq!(
+            swiss_pairs(&ratings, &BTreeSet::new()),
+            vec![(1, 3), (0, 2)]
+        );
+        let played = BTreeSet::from([(1, 3), (0, 2)]);
+        assert_eq!(swiss_pairs(&ratings, &played), vec![(0, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn round_robin() {
+        let tournament = Tournament {
+            scenario: Scenario {
+                area: Rect::from_points([Position::new(0., 0.), Position::new(50., 50.)]),
+                rules: Rules::new(GameMode::Infection),
+                player_count: 6,
+                team_count: 2,
+                step_limit: 100,
+                end_conditions: Vec::new(),
+            },
+            strategies: vec![Strategy::Default, Strategy::Naive, Strategy::Default],
+            seeds: 2,
+            pairing: Pairing::RoundRobin,
+        };
+        let report = tournament.run();
+        assert_eq!(report.games.len(), 3 * 2 * 2);
+        for rating in &report.ratings {
+            assert_eq!(rating.wins + rating.draws + rating.losses, 8);
+            assert!(rating.low.is_finite() && rating.low <= rating.high);
+        }
+        // the same strategy entered twice can't be told apart
+        let (first, second) = (&report.ratings[0], &report.ratings[2]);
+        assert!(first.low <= second.high && second.low <= first.high);
+    }
+}
@@ -0,0 +1,307 @@
+use std::io::{self, Write};
+
+use crate::environment::*;
+
+/// Writes one row per player per step describing where the player is and what they did, rows are streamed out as
+/// the simulation runs so that long runs don't need to fit in memory
+pub trait TrajectoryWriter {
+    /// Write the rows for a step, with the state of each player still playing after the step and the action they took
+    /// during it
+    fn write_step(
+        &mut self,
+        environment: &TagEnvironment,
+        actions: &[TagPlayerAction],
+    ) -> io::Result<()>;
+
+    /// Write anything that is still buffered, must be called once the last step has been written
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// One row of the trajectory
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct TrajectoryRow {
+    step: u64,
+    player_id: PlayerId,
+    x: f32,
+    y: f32,
+    status: StatusKind,
+    action: ActionKind,
+    dx: f32,
+    dy: f32,
+    target: Option<PlayerId>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum StatusKind {
+    NotIt = 0,
+    It = 1,
+    Frozen = 2,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum ActionKind {
+    Run = 0,
+    Tag = 1,
+    Unfreeze = 2,
+}
+
+impl StatusKind {
+    fn name(self) -> &'static str {
+        match self {
+            StatusKind::NotIt => "not_it",
+            StatusKind::It => "it",
+            StatusKind::Frozen => "frozen",
+        }
+    }
+}
+
+impl ActionKind {
+    fn name(self) -> &'static str {
+        match self {
+            ActionKind::Run => "run",
+            ActionKind::Tag => "tag",
+            ActionKind::Unfreeze => "unfreeze",
+        }
+    }
+}
+
+/// The rows for a step, players who have been removed from the game have none
+fn rows<'a>(
+    environment: &'a TagEnvironment,
+    actions: &'a [TagPlayerAction],
+) -> impl Iterator<Item = TrajectoryRow> + 'a {
+    assert_eq!(environment.player_state().len(), actions.len());
+    let step = environment.step();
+    environment.players().map(move |(player_id, state)| {
+        let status = match state.status {
+            TagStatus::NotIt => StatusKind::NotIt,
+            TagStatus::It { .. } => StatusKind::It,
+            TagStatus::Frozen { .. } => StatusKind::Frozen,
+        };
+        let (action, stretch, target) = match actions[player_id] {
+            TagPlayerAction::Run { stretch } => (ActionKind::Run, stretch, None),
+            TagPlayerAction::Tag { player_id } => {
+                (ActionKind::Tag, Default::default(), Some(player_id))
+            }
+            TagPlayerAction::Unfreeze { player_id } => {
+                (ActionKind::Unfreeze, Default::default(), Some(player_id))
+            }
+        };
+        TrajectoryRow {
+            step,
+            player_id,
+            x: state.position.x,
+            y: state.position.y,
+            status,
+            action,
+            dx: stretch.x,
+            dy: stretch.y,
+            target,
+        }
+    })
+}
+
+/// Writes the trajectory as CSV with a header row, the tag target is empty for runs
+pub struct CsvTrajectoryWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CsvTrajectoryWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, "step,player_id,x,y,status,action,dx,dy,target")?;
+        Ok(Self { out })
+    }
+}
+
+impl<W: Write> TrajectoryWriter for CsvTrajectoryWriter<W> {
+    fn write_step(
+        &mut self,
+        environment: &TagEnvironment,
+        actions: &[TagPlayerAction],
+    ) -> io::Result<()> {
+        for row in rows(environment, actions) {
+            write!(
+                self.out,
+                "{},{},{},{},{},{},{},{},",
+                row.step,
+                row.player_id,
+                row.x,
+                row.y,
+                row.status.name(),
+                row.action.name(),
+                row.dx,
+                row.dy
+            )?;
+            if let Some(target) = row.target {
+                write!(self.out, "{}", target)?;
+            }
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Identifies the columnar trajectory format, followed by a version byte
+const COLUMNAR_MAGIC: &[u8; 4] = b"TAGT";
+const COLUMNAR_VERSION: u8 = 1;
+/// The number of rows buffered before a row group is written
+const ROW_GROUP_SIZE: usize = 4096;
+
+/// Writes the trajectory in a columnar binary format.
+///
+/// The file starts with `TAGT` and a version byte, followed by row groups. Each row group starts with its row count
+/// as a little endian `u32`, then each column in turn with all the values for the rows in the group: step `u64`,
+/// player_id `u32`, x `f32`, y `f32`, status `u8` (0 not it, 1 it, 2 frozen), action `u8` (0 run, 1 tag,
+/// 2 unfreeze), dx `f32`, dy `f32` and target `i32` (-1 for none), all little endian. A row group with a row count of
+/// 0 marks the end of the file.
+pub struct ColumnarTrajectoryWriter<W: Write> {
+    out: W,
+    rows: Vec<TrajectoryRow>,
+}
+
+impl<W: Write> ColumnarTrajectoryWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(COLUMNAR_MAGIC)?;
+        out.write_all(&[COLUMNAR_VERSION])?;
+        Ok(Self {
+            out,
+            rows: Vec::with_capacity(ROW_GROUP_SIZE),
+        })
+    }
+
+    fn write_row_group(&mut self) -> io::Result<()> {
+        let out = &mut self.out;
+        let rows = &self.rows;
+        out.write_all(&(rows.len() as u32).to_le_bytes())?;
+        for row in rows {
+            out.write_all(&row.step.to_le_bytes())?;
+        }
+        for row in rows {
+            out.write_all(&(row.player_id as u32).to_le_bytes())?;
+        }
+        for row in rows {
+            out.write_all(&row.x.to_le_bytes())?;
+        }
+        for row in rows {
+            out.write_all(&row.y.to_le_bytes())?;
+        }
+        for row in rows {
+            out.write_all(&[row.status as u8])?;
+        }
+        for row in rows {
+            out.write_all(&[row.action as u8])?;
+        }
+        for row in rows {
+            out.write_all(&row.dx.to_le_bytes())?;
+        }
+        for row in rows {
+            out.write_all(&row.dy.to_le_bytes())?;
+        }
+        for row in rows {
+            let target = row.target.map_or(-1, |target| target as i32);
+            out.write_all(&target.to_le_bytes())?;
+        }
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> TrajectoryWriter for ColumnarTrajectoryWriter<W> {
+    fn write_step(
+        &mut self,
+        environment: &TagEnvironment,
+        actions: &[TagPlayerAction],
+    ) -> io::Result<()> {
+        for row in rows(environment, actions) {
+            self.rows.push(row);
+            if self.rows.len() == ROW_GROUP_SIZE {
+                self.write_row_group()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.rows.is_empty() {
+            self.write_row_group()?;
+        }
+        self.write_row_group()?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stand_still(environment: &mut TagEnvironment) {
+        let players = environment.player_state().len();
+        environment.apply_actions(&vec![
+            TagPlayerAction::Run {
+                stretch: (0., 0.).into()
+            };
+            players
+        ]);
+    }
+
+    /// The environment after 3 steps standing still, and actions for the next step
+    fn step() -> (TagEnvironment, Vec<TagPlayerAction>) {
+        let actions = vec![
+            TagPlayerAction::Tag { player_id: 1 },
+            TagPlayerAction::Run {
+                stretch: (0.5, -1.).into(),
+            },
+        ];
+        let mut environment = test_environment((10., 10.), &[(1., 2.), (1.5, 2.)]);
+        for _ in 0..3 {
+            stand_still(&mut environment);
+        }
+        (environment, actions)
+    }
+
+    #[test]
+    fn write_csv() {
+        let (mut environment, actions) = step();
+        let mut csv = Vec::new();
+        let mut writer = CsvTrajectoryWriter::new(&mut csv).unwrap();
+        writer.write_step(&environment, &actions).unwrap();
+        // removed players have no rows
+        environment.remove_player(1);
+        stand_still(&mut environment);
+        writer.write_step(&environment, &actions).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "step,player_id,x,y,status,action,dx,dy,target\n\
+            3,0,1,2,it,tag,0,0,1\n\
+            3,1,1.5,2,not_it,run,0.5,-1,\n\
+            4,0,1,2,it,tag,0,0,1\n"
+        );
+    }
+
+    #[test]
+    fn write_columnar() {
+        let (mut environment, actions) = step();
+        let mut bytes = Vec::new();
+        let mut writer = ColumnarTrajectoryWriter::new(&mut bytes).unwrap();
+        writer.write_step(&environment, &actions).unwrap();
+        stand_still(&mut environment);
+        writer.write_step(&environment, &actions).unwrap();
+        writer.finish().unwrap();
+
+        let row_bytes = 8 + 4 + 4 + 4 + 1 + 1 + 4 + 4 + 4;
+        assert_eq!(bytes.len(), 5 + 4 + 4 * row_bytes + 4);
+        assert_eq!(&bytes[..5], b"TAGT\x01");
+        assert_eq!(bytes[5..9], 4u32.to_le_bytes());
+        assert_eq!(bytes[9..17], 3u64.to_le_bytes());
+        assert_eq!(bytes[33..41], 4u64.to_le_bytes());
+        assert_eq!(bytes[45..49], 1u32.to_le_bytes());
+        assert_eq!(bytes[bytes.len() - 4..], 0u32.to_le_bytes());
+    }
+}
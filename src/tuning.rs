@@ -0,0 +1,224 @@
+use std::f32::consts::TAU;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{
+    agent::{AgentParams, TagPlayerAgent},
+    scenario::Scenario,
+    simulation::StepStatus,
+    strategy::Strategy,
+};
+
+/// How many of the best genomes are carried over unchanged to the next generation
+const ELITES: usize = 2;
+/// How many genomes compete to be chosen as each parent
+const SELECTION_SIZE: usize = 3;
+/// The chance of each parameter of a child being mutated
+const MUTATION_RATE: f64 = 0.3;
+/// Standard deviation of a mutation, as a fraction of the parameter's range
+const MUTATION_SCALE: f32 = 0.1;
+
+/// Evolves the parameters of the player agent with a genetic algorithm, by playing them against another strategy
+#[derive(Clone, Debug)]
+pub struct Tuner {
+    pub scenario: Scenario,
+    /// The strategy which plays the other side
+    pub opponent: Strategy,
+    pub fitness: Fitness,
+    /// How many genomes there are in each generation
+    pub population: usize,
+    pub generations: usize,
+    /// Each genome plays a game with each seed from 0 up to this, on both sides
+    pub seeds: u64,
+}
+
+/// What the tuned parameters are trying to maximise
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fitness {
+    /// The mean fraction of the steps each player spends neither it nor frozen
+    FreeTime,
+    /// The mean number of tags each player makes per step
+    TagsPerStep,
+}
+
+/// Parameters along with how well they played
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Individual {
+    pub params: AgentParams,
+    pub fitness: f64,
+}
+
+impl Tuner {
+    /// Evolve the parameters starting from the default parameters and random ones, after each generation is played
+    /// it is passed to `on_generation` sorted with the fittest first. Returns the fittest parameters found
+    pub fn run(&self, mut on_generation: impl FnMut(usize, &[Individual])) -> Individual {
+        assert!(
+            self.population > ELITES,
+            "The population needs more than {} genomes",
+            ELITES
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut genomes: Vec<[f32; 5]> = std::iter::once(AgentParams::default().to_vector())
+            .chain((1..self.population).map(|_| random_genome(&mut rng)))
+            .collect();
+        let mut fittest = None;
+        for generation in 0..self.generations {
+            let mut individuals: Vec<_> = genomes
+                .par_iter()
+                .map(|genome| {
+                    let params = AgentParams::from_vector(*genome);
+                    Individual {
+                        params,
+                        fitness: self.evaluate(params),
+                    }
+                })
+                .collect();
+            individuals.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+            on_generation(generation, &individuals);
+            fittest = Some(individuals[0]);
+
+            genomes = individuals
+                .iter()
+                .take(ELITES)
+                .map(|individual| individual.params.to_vector())
+                .collect();
+            while genomes.len() < self.population {
+                let a = select(&mut rng, &individuals);
+                let b = select(&mut rng, &individuals);
+                let child = crossover(&mut rng, a, b);
+                genomes.push(mutate(&mut rng, child));
+            }
+        }
+        fittest.expect("At least one generation needs to be played")
+    }
+
+    /// Play the parameters against the opponent with each seed on both sides, the mean fitness of the players using
+    /// the parameters
+    pub fn evaluate(&self, params: AgentParams) -> f64 {
+        let mut total = 0.;
+        for seed in 0..self.seeds {
+            for tuned_side in 0..2 {
+                let mut simulation = self.scenario.simulation(seed, |player_id| {
+                    if self.scenario.side(player_id) == tuned_side {
+                        Box::new(TagPlayerAgent { params })
+                    } else {
+                        self.opponent.agent()
+                    }
+                });
+                while simulation.step().expect("Failed agents skip their turns")
+                    == StepStatus::Running
+                {}
+
+                let outcome = simulation.outcome();
+                let tuned_players: Vec<_> = outcome
+                    .statistics
+                    .players()
+                    .iter()
+                    .enumerate()
+                    .filter(|(player_id, _)| self.scenario.side(*player_id) == tuned_side)
+                    .map(|(_, stats)| stats)
+                    .collect();
+                let side_total: u64 = tuned_players
+                    .iter()
+                    .map(|stats| match self.fitness {
+                        Fitness::FreeTime => stats.steps_free,
+                        Fitness::TagsPerStep => stats.tags_made,
+                    })
+                    .sum();
+                total += side_total as f64 / tuned_players.len() as f64 / outcome.steps as f64;
+            }
+        }
+        total / (self.seeds * 2) as f64
+    }
+}
+
+fn random_genome(rng: &mut StdRng) -> [f32; 5] {
+    AgentParams::BOUNDS.map(|(low, high)| rng.gen_range(low..=high))
+}
+
+/// Pick the fittest of a few random individuals
+fn select(rng: &mut StdRng, individuals: &[Individual]) -> [f32; 5] {
+    (0..SELECTION_SIZE)
+        .map(|_| &individuals[rng.gen_range(0..individuals.len())])
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .unwrap()
+        .params
+        .to_vector()
+}
+
+/// Take each parameter from either parent
+fn crossover(rng: &mut StdRng, a: [f32; 5], b: [f32; 5]) -> [f32; 5] {
+    let mut child = a;
+    for (gene, b_gene) in child.iter_mut().zip(b) {
+        if rng.gen_bool(0.5) {
+            *gene = b_gene;
+        }
+    }
+    child
+}
+
+/// Nudge some of the parameters by a normally distributed amount, keeping them within their bounds
+fn mutate(rng: &mut StdRng, mut genome: [f32; 5]) -> [f32; 5] {
+    for (gene, (low, high)) in genome.iter_mut().zip(AgentParams::BOUNDS) {
+        if rng.gen_bool(MUTATION_RATE) {
+            // Box-Muller transform of two uniform samples
+            let normal = (-2. * rng.gen_range(f32::EPSILON..1.).ln()).sqrt()
+                * (TAU * rng.gen::<f32>()).cos();
+            *gene = (*gene + normal * MUTATION_SCALE * (high - low)).clamp(low, high);
+        }
+    }
+    genome
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::{
+        environment::Position,
+        rules::{GameMode, Rules},
+    };
+
+    #[test]
+    fn fittest_survive() {
+        let tuner = Tuner {
+            scenario: Scenario {
+                area: Rect::from_points([Position::new(0., 0.), Position::new(30., 30.)]),
+                rules: Rules::new(GameMode::Infection),
+                player_count: 6,
+                team_count: 2,
+                step_limit: 50,
+                end_conditions: Vec::new(),
+            },
+            opponent: Strategy::Default,
+            fitness: Fitness::FreeTime,
+            population: 5,
+            generations: 3,
+            seeds: 1,
+        };
+        let mut best = Vec::new();
+        let fittest = tuner.run(|generation, individuals| {
+            assert_eq!(individuals.len(), 5);
+            if generation == 0 {
+                assert!(individuals
+                    .iter()
+                    .any(|individual| individual.params == AgentParams::default()));
+            }
+            best.push(individuals[0].fitness);
+        });
+        assert_eq!(best.len(), 3);
+        assert!(best.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(fittest.fitness, best[2]);
+        assert_eq!(tuner.evaluate(fittest.params), fittest.fitness);
+        for (value, (low, high)) in fittest
+            .params
+            .to_vector()
+            .into_iter()
+            .zip(AgentParams::BOUNDS)
+        {
+            assert!((low..=high).contains(&value));
+        }
+    }
+}
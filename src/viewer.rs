@@ -1,69 +1,446 @@
-use std::fmt;
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Write},
+};
+
+use crossterm::{
+    cursor, execute, queue,
+    style::{Color, Print, Stylize},
+    terminal::{self, ClearType},
+};
 
 use crate::{
-    environment::{PlayArea, Position, TagPlayerAction},
+    braille::{render_braille, BrailleCanvas},
+    environment::*,
+    rules::GameMode,
     simulation::Simulation,
 };
 
-/// Render the current state of the simulation and the actions to the canvas
-pub fn render_frame<const WIDTH: usize, const HEIGHT: usize>(
+/// Colours which are easy to tell apart, the players or in team tag the teams take them in turn
+const PALETTE: [Color; 10] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+    Color::DarkCyan,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkMagenta,
+];
+/// The background of the jails
+const SHADE: Color = Color::DarkGrey;
+/// How many steps a player is dimmed for after they have been tagged
+const RECENTLY_TAGGED: u64 = 10;
+/// Terminal characters are about twice as tall as they are wide
+const CELL_ASPECT: f32 = 2.;
+/// Rows kept below the field for messages, so that the field doesn't change size with every event
+const MESSAGE_LINES: usize = 3;
+
+/// How many steps of each player's path are drawn behind them by the Braille renderer
+const TRAIL_STEPS: usize = 8;
+
+/// How the field is drawn
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Renderer {
+    /// A character for each player
+    #[default]
+    Ascii,
+    /// Braille dots, with the reach of it and the players' trails
+    Braille,
+}
+
+/// How the game is shown
+#[derive(Debug)]
+pub struct View {
+    /// Marked on the field
+    humans: Vec<PlayerId>,
+    colour: bool,
+    /// The columns and rows the field has to fit in, otherwise it fits in the terminal
+    canvas_size: Option<(usize, usize)>,
+    renderer: Renderer,
+    /// Where the players were for the last few steps, oldest first, none for players who weren't playing
+    trails: VecDeque<Vec<Option<Position>>>,
+}
+
+impl View {
+    pub fn new(
+        humans: Vec<PlayerId>,
+        colour: bool,
+        canvas_size: Option<(usize, usize)>,
+        renderer: Renderer,
+    ) -> Self {
+        Self {
+            humans,
+            colour,
+            canvas_size,
+            renderer,
+            trails: VecDeque::with_capacity(TRAIL_STEPS + 1),
+        }
+    }
+
+    /// Remember where the players are before the next step, for their trails
+    pub fn record(&mut self, environment: &TagEnvironment) {
+        if self.renderer != Renderer::Braille {
+            return;
+        }
+        self.trails.push_back(
+            environment
+                .player_state()
+                .iter()
+                .enumerate()
+                .map(|(player_id, player)| {
+                    environment.is_playing(player_id).then_some(player.position)
+                })
+                .collect(),
+        );
+        if self.trails.len() > TRAIL_STEPS {
+            self.trails.pop_front();
+        }
+    }
+
+    /// The field fitting in the columns and rows, unless the view has its own size
+    pub fn canvas(&self, simulation: &Simulation, columns: usize, rows: usize) -> String {
+        let (columns, rows) = self.canvas_size.unwrap_or((columns, rows));
+        let area = simulation.environment().area();
+        match self.renderer {
+            Renderer::Ascii => {
+                let mut canvas = TagCanvas::new(area, columns, rows).coloured(self.colour);
+                render_frame(simulation, simulation.actions(), &self.humans, &mut canvas);
+                canvas.to_string()
+            }
+            Renderer::Braille => {
+                let mut canvas = BrailleCanvas::new(area, columns, rows).coloured(self.colour);
+                render_braille(
+                    simulation.environment(),
+                    &self.humans,
+                    &self.trails,
+                    &mut canvas,
+                );
+                canvas.to_string()
+            }
+        }
+    }
+
+    /// The field with the messages below it and the side panel to its right, fitting in a terminal of the size
+    pub fn full_screen(
+        &self,
+        simulation: &Simulation,
+        messages: &[String],
+        paused: bool,
+        tick_rate: f64,
+        (columns, rows): (u16, u16),
+    ) -> String {
+        let rows = rows as usize;
+        let panel = side_panel(simulation, paused, tick_rate, self.colour, rows);
+        let panel_width = panel
+            .iter()
+            .map(|line| visible_width(line))
+            .max()
+            .unwrap_or(0);
+        let canvas = self.canvas(
+            simulation,
+            (columns as usize).saturating_sub(panel_width + 2),
+            // the canvas has a header line
+            rows.saturating_sub(messages.len().max(MESSAGE_LINES) + 1),
+        );
+        // the messages get the rows the field leaves, drawing more than the terminal has would scroll it
+        let frame: Vec<&str> = canvas
+            .lines()
+            .chain(messages.iter().map(String::as_str))
+            .take(rows)
+            .collect();
+        beside(&frame.join("\n"), &panel)
+    }
+}
+
+/// The keys the full screen viewer listens to, shown at the bottom of the side panel
+const VIEWER_KEYS: [&str; 3] = ["p pause   n step", "+ faster  - slower", "q quit"];
+
+/// Render the current state of the simulation and the actions to the canvas, the human players are marked
+fn render_frame(
     simulation: &Simulation,
     actions: &[TagPlayerAction],
-    canvas: &mut TagCanvas<WIDTH, HEIGHT>,
+    humans: &[PlayerId],
+    canvas: &mut TagCanvas,
 ) {
-    for (player, action) in simulation.player_state().iter().zip(actions) {
+    let environment = simulation.environment();
+    if environment.rules().mode == GameMode::Teams {
+        for team in 0..environment.team_scores().len() {
+            canvas.shade(environment.jail(team));
+        }
+    }
+    for (player_id, player) in environment.players() {
         canvas.set(
             player.position,
-            if matches!(action, TagPlayerAction::Tag { .. }) {
+            if humans.contains(&player_id) {
+                DrawCell::Human
+            } else if matches!(actions.get(player_id), Some(TagPlayerAction::Tag { .. })) {
                 DrawCell::YoureIt
             } else if player.is_it() {
                 DrawCell::It
+            } else if player.is_frozen() {
+                DrawCell::Frozen
             } else {
                 DrawCell::Runner
             },
+            paint(environment, player_id),
         );
     }
 }
 
+/// How a player is coloured: by who they are, or their team in team tag, highlighted when they are it and dimmed
+/// when they have just been tagged
+pub fn paint(environment: &TagEnvironment, player_id: PlayerId) -> Paint {
+    let player = environment.get_state(player_id);
+    let colour = if environment.rules().mode == GameMode::Teams {
+        player.team
+    } else {
+        player_id
+    };
+    Paint {
+        colour: PALETTE[colour % PALETTE.len()],
+        highlight: player.is_it(),
+        dim: match player.status {
+            TagStatus::It {
+                tagged_by,
+                tagged_at,
+            } => tagged_by != player_id && environment.step() - tagged_at < RECENTLY_TAGGED,
+            _ => false,
+        },
+    }
+}
+
+/// The side panel of the full screen viewer: the step, who is it, how many tags have been made and a row for each
+/// player still in the game, with at most the number of rows so players who don't fit are left out
+fn side_panel(
+    simulation: &Simulation,
+    paused: bool,
+    tick_rate: f64,
+    colour: bool,
+    rows: usize,
+) -> Vec<String> {
+    let environment = simulation.environment();
+    let statistics = simulation.statistics();
+    let it: Vec<_> = environment
+        .get_its()
+        .map(|(player_id, _)| player_id.to_string())
+        .collect();
+    let mut lines = vec![
+        format!("Step {}", environment.step()),
+        if paused {
+            String::from("Paused")
+        } else {
+            format!("{} steps per second", tick_rate)
+        },
+        format!(
+            "It: {}",
+            if it.is_empty() {
+                String::from("nobody")
+            } else {
+                it.join(", ")
+            }
+        ),
+        format!("Tags: {}", statistics.tags()),
+        String::new(),
+        format!(
+            "{:>6}   {:>5} {:>4} {:>6}",
+            "player", "score", "tags", "tagged"
+        ),
+    ];
+    let players: Vec<_> = environment.players().collect();
+    // room for the players after the keys and a blank line, less a line saying how many are left out
+    let room = rows.saturating_sub(lines.len() + VIEWER_KEYS.len() + 1);
+    let shown = if players.len() > room {
+        room.saturating_sub(1)
+    } else {
+        players.len()
+    };
+    for &(player_id, player) in &players[..shown] {
+        let stats = &statistics.players()[player_id];
+        let status = if player.is_it() {
+            "*"
+        } else if player.is_frozen() {
+            "x"
+        } else {
+            "."
+        };
+        let status = if colour {
+            paint(environment, player_id).apply(status)
+        } else {
+            status.to_owned()
+        };
+        lines.push(format!(
+            "{:>6} {} {:>5} {:>4} {:>6}",
+            player_id, status, stats.steps_free, stats.tags_made, stats.times_tagged
+        ));
+    }
+    if shown < players.len() {
+        lines.push(format!("+{} more", players.len() - shown));
+    }
+    lines.push(String::new());
+    lines.extend(VIEWER_KEYS.map(String::from));
+    lines.truncate(rows);
+    lines
+}
+
+/// Lay the panel out to the right of the text, padding the text so that the panel lines up
+fn beside(text: &str, panel: &[String]) -> String {
+    let width = text.lines().map(visible_width).max().unwrap_or(0);
+    let lines = text.lines().count().max(panel.len());
+    let mut text_lines = text.lines();
+    let mut laid_out = String::new();
+    for line in 0..lines {
+        let left = text_lines.next().unwrap_or("");
+        let right = panel.get(line).map_or("", String::as_str);
+        let padding = " ".repeat(width - visible_width(left) + 2);
+        laid_out += format!("{}{}{}", left, padding, right).trim_end();
+        laid_out.push('\n');
+    }
+    laid_out
+}
+
+/// How many columns a line takes up in the terminal, leaving out colour escape sequences
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // a control sequence ends with a letter
+            chars.find(char::is_ascii_alphabetic);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// How a player is coloured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Paint {
+    pub colour: Color,
+    /// Shown in reverse
+    pub highlight: bool,
+    pub dim: bool,
+}
+
+impl Paint {
+    /// The text with the escape sequences to colour it
+    pub fn apply(&self, text: &str) -> String {
+        let mut styled = text.with(self.colour);
+        if self.highlight {
+            styled = styled.reverse();
+        }
+        if self.dim {
+            styled = styled.dim();
+        }
+        styled.to_string()
+    }
+}
+
 /// Ascii art canvas for a tag game
-pub struct TagCanvas<const WIDTH: usize, const HEIGHT: usize> {
+pub struct TagCanvas {
     area: PlayArea,
-    grid: [[DrawCell; WIDTH]; HEIGHT],
+    /// Rows of cells
+    grid: Vec<Vec<Cell>>,
+    /// Whether to colour the players and shade the jails with ANSI escape sequences
+    colour: bool,
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> TagCanvas<WIDTH, HEIGHT> {
-    /// New canvas for drawing a particular playing field
-    pub fn new(area: PlayArea) -> Self {
+/// What's drawn in a cell of the canvas
+#[derive(Clone, Copy)]
+struct Cell {
+    draw: DrawCell,
+    paint: Option<Paint>,
+    shaded: bool,
+}
+
+impl TagCanvas {
+    /// New canvas for drawing a particular playing field, as large as fits in the number of columns and rows while
+    /// keeping the shape of the field
+    pub fn new(area: PlayArea, columns: usize, rows: usize) -> Self {
+        let (width, height) = fit(area, columns, rows);
         Self {
             area,
-            grid: [[DrawCell::None; WIDTH]; HEIGHT],
+            grid: vec![
+                vec![
+                    Cell {
+                        draw: DrawCell::None,
+                        paint: None,
+                        shaded: false,
+                    };
+                    width
+                ];
+                height
+            ],
+            colour: false,
         }
     }
 
-    /// Set what should be rendered in a cell. Only overwrites if the cell is more important than the existing cell
-    pub fn set(&mut self, position: Position, cell: DrawCell) {
-        let x = (position.x / self.area.width() * (WIDTH - 1) as f32) as usize;
-        let y = (position.y / self.area.height() * (HEIGHT - 1) as f32) as usize;
+    /// Draw in colour
+    pub fn coloured(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    /// Set what should be rendered in a cell and how it's coloured. Only overwrites if the cell is more important
+    /// than the existing cell
+    pub fn set(&mut self, position: Position, cell: DrawCell, paint: Paint) {
+        let (x, y) = self.cell_at(position);
         let existing_cell = &mut self.grid[y][x];
-        if cell > *existing_cell {
-            *existing_cell = cell;
+        if cell > existing_cell.draw {
+            existing_cell.draw = cell;
+            existing_cell.paint = Some(paint);
         }
     }
+
+    /// Shade the background of part of the field
+    pub fn shade(&mut self, area: PlayArea) {
+        let (min_x, min_y) = self.cell_at(area.min());
+        let (max_x, max_y) = self.cell_at(area.max());
+        for row in &mut self.grid[min_y..=max_y] {
+            for cell in &mut row[min_x..=max_x] {
+                cell.shaded = true;
+            }
+        }
+    }
+
+    /// The column and row of the cell a position is in
+    fn cell_at(&self, position: Position) -> (usize, usize) {
+        let (width, height) = (self.grid[0].len(), self.grid.len());
+        let x = (position.x - self.area.min_x()) / self.area.width() * (width - 1) as f32;
+        let y = (position.y - self.area.min_y()) / self.area.height() * (height - 1) as f32;
+        (
+            (x.max(0.) as usize).min(width - 1),
+            (y.max(0.) as usize).min(height - 1),
+        )
+    }
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> fmt::Display for TagCanvas<WIDTH, HEIGHT> {
+impl fmt::Display for TagCanvas {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "==================================")?;
-        for row in self.grid {
+        for row in &self.grid {
             let mut x = 0;
             while x < row.len() {
-                let chars = match row[x] {
+                let cell = row[x];
+                let chars = match cell.draw {
                     DrawCell::None => " ",
                     DrawCell::YoureIt => "*-You're It!",
                     DrawCell::It => "*",
                     DrawCell::Runner => ".",
+                    DrawCell::Frozen => "x",
+                    DrawCell::Human => "@",
                 };
-                write!(f, "{}", chars)?;
+                // labels are cut short at the edge of the field
+                let chars = &chars[..chars.len().min(row.len() - x)];
+                if self.colour {
+                    write_coloured(f, chars, cell.paint, cell.shaded)?;
+                } else {
+                    write!(f, "{}", chars)?;
+                }
                 x += chars.len();
             }
             writeln!(f)?;
@@ -78,8 +455,232 @@ pub enum DrawCell {
     None = 0,
     /// a player here isn't it
     Runner = 1,
+    /// a player here is frozen
+    Frozen = 2,
     /// a player here is it
-    It = 2,
+    It = 3,
     /// a player here was it and just tagged another player
-    YoureIt = 3,
+    YoureIt = 4,
+    /// a human player is here
+    Human = 5,
+}
+
+/// The columns and rows of a canvas for the field which is as large as fits in the columns and rows while keeping the
+/// shape of the field
+pub fn fit(area: PlayArea, columns: usize, rows: usize) -> (usize, usize) {
+    // the width of the field in columns for each row of its height
+    let aspect = area.width() / area.height() * CELL_ASPECT;
+    let width = (columns as f32).min(rows as f32 * aspect).max(2.);
+    let height = (width / aspect).round().max(2.);
+    (width as usize, height as usize)
+}
+
+/// Write the text of a cell in its colour, on the shaded background if it's shaded
+pub fn write_coloured(
+    f: &mut fmt::Formatter<'_>,
+    text: &str,
+    paint: Option<Paint>,
+    shaded: bool,
+) -> fmt::Result {
+    match paint {
+        Some(paint) if shaded => write!(f, "{}", paint.apply(text).on(SHADE)),
+        Some(paint) => write!(f, "{}", paint.apply(text)),
+        None if shaded => write!(f, "{}", text.on(SHADE)),
+        None => write!(f, "{}", text),
+    }
+}
+
+/// The terminal in raw mode showing an alternate screen which is redrawn in place, until it is dropped
+pub struct Screen {
+    out: io::Stdout,
+}
+
+impl Screen {
+    pub fn enter() -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self { out })
+    }
+
+    /// The columns and rows of the terminal, which change when it's resized
+    pub fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    /// Replace what's on the screen with the text
+    pub fn draw(&mut self, text: &str) -> io::Result<()> {
+        queue!(self.out, cursor::MoveTo(0, 0))?;
+        for (row, line) in text.lines().enumerate() {
+            // moving down rather than starting a new line, which would scroll a full screen
+            queue!(
+                self.out,
+                cursor::MoveTo(0, row as u16),
+                Print(line),
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        queue!(
+            self.out,
+            cursor::MoveToNextLine(1),
+            terminal::Clear(ClearType::FromCursorDown)
+        )?;
+        self.out.flush()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Rect;
+
+    use super::*;
+    use crate::{
+        agent::{Agent, NaiveAgent},
+        rules::Rules,
+    };
+
+    #[test]
+    fn colour_only_changes_the_escape_sequences() {
+        let simulation = Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(10., 10.)]),
+            Rules::default(),
+            [
+                (
+                    (5., 5.),
+                    TagStatus::It {
+                        tagged_by: 0,
+                        tagged_at: 0,
+                    },
+                ),
+                ((1., 8.), TagStatus::NotIt),
+            ]
+            .map(|(position, status)| {
+                (
+                    Box::new(NaiveAgent) as Box<dyn Agent>,
+                    TagPlayerVisibleState {
+                        position: position.into(),
+                        status,
+                        team: 0,
+                    },
+                )
+            }),
+        );
+        let actions = [TagPlayerAction::Run {
+            stretch: euclid::default::Vector2D::zero(),
+        }; 2];
+        let mut plain = TagCanvas::new(simulation.environment().area(), 20, 10);
+        let mut coloured = TagCanvas::new(simulation.environment().area(), 20, 10).coloured(true);
+        render_frame(&simulation, &actions, &[1], &mut plain);
+        render_frame(&simulation, &actions, &[1], &mut coloured);
+        let (plain, coloured) = (plain.to_string(), coloured.to_string());
+
+        assert!(!plain.contains('\x1b'));
+        assert!(coloured.contains('\x1b'));
+        for (plain, coloured) in plain.lines().zip(coloured.lines()) {
+            assert_eq!(plain.chars().count(), visible_width(coloured));
+        }
+        let panel = side_panel(&simulation, false, 10., true, usize::MAX);
+        assert_eq!(
+            beside(&coloured, &panel).lines().nth(6).map(visible_width),
+            beside(
+                &plain,
+                &side_panel(&simulation, false, 10., false, usize::MAX)
+            )
+            .lines()
+            .nth(6)
+            .map(visible_width)
+        );
+    }
+
+    #[test]
+    fn canvas_keeps_the_shape_of_the_field() {
+        let size = |columns, rows| {
+            let canvas = TagCanvas::new(
+                Rect::from_points([Position::new(0., 0.), Position::new(100., 50.)]),
+                columns,
+                rows,
+            );
+            (canvas.grid[0].len(), canvas.grid.len())
+        };
+        assert_eq!(size(170, 50), (170, 43));
+        assert_eq!(size(400, 50), (200, 50));
+        assert_eq!(size(80, 24), (80, 20));
+        assert_eq!(size(0, 0), (2, 2));
+    }
+
+    #[test]
+    fn full_screen_fits_the_terminal() {
+        let players = (0..40).map(|player_id| {
+            (
+                Box::new(NaiveAgent) as Box<dyn Agent>,
+                TagPlayerVisibleState {
+                    position: (player_id as f32, 5.).into(),
+                    status: if player_id == 0 {
+                        TagStatus::It {
+                            tagged_by: 0,
+                            tagged_at: 0,
+                        }
+                    } else {
+                        TagStatus::NotIt
+                    },
+                    team: 0,
+                },
+            )
+        });
+        let simulation = Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(100., 100.)]),
+            Rules::default(),
+            players,
+        );
+        let messages: Vec<String> = (0..10).map(|message| message.to_string()).collect();
+        let view = View::new(Vec::new(), false, None, Renderer::Ascii);
+
+        let screen = view.full_screen(&simulation, &messages, false, 10., (80, 20));
+        assert!(screen.lines().count() <= 20);
+        assert!(screen.contains("more"));
+        // even when the field is given a size which is too large
+        let view = View::new(Vec::new(), false, Some((80, 40)), Renderer::Ascii);
+        let screen = view.full_screen(&simulation, &messages, false, 10., (80, 20));
+        assert!(screen.lines().count() <= 20);
+    }
+
+    #[test]
+    fn labels_stay_on_the_canvas() {
+        let simulation = Simulation::new(
+            Rect::from_points([Position::new(0., 0.), Position::new(10., 10.)]),
+            Rules::default(),
+            [(10., 5.), (7.5, 2.)].map(|position| {
+                (
+                    Box::new(NaiveAgent) as Box<dyn Agent>,
+                    TagPlayerVisibleState {
+                        position: position.into(),
+                        status: TagStatus::It {
+                            tagged_by: 0,
+                            tagged_at: 0,
+                        },
+                        team: 0,
+                    },
+                )
+            }),
+        );
+        let mut canvas = TagCanvas::new(simulation.environment().area(), 20, 10);
+        render_frame(
+            &simulation,
+            &[TagPlayerAction::Tag { player_id: 1 }; 2],
+            &[],
+            &mut canvas,
+        );
+        let frame = canvas.to_string();
+        assert!(frame.contains("*-You"));
+        for row in frame.lines().skip(1) {
+            assert_eq!(row.len(), 20);
+        }
+    }
 }
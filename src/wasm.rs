@@ -0,0 +1,195 @@
+use std::fmt;
+
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::{
+    agent::Agent,
+    environment::*,
+    observation::{observe, Action, DISCRETE_ACTIONS},
+};
+
+/// Fuel a module gets to choose each action, roughly the number of instructions it can run
+const FUEL_PER_STEP: u64 = 1_000_000;
+/// Fuel a module gets to set itself up when it is loaded
+const STARTUP_FUEL: u64 = 10_000_000;
+/// The most memory a module can use, in bytes
+const MEMORY_LIMIT: usize = 16 << 20;
+/// The action code for a continuous action, which is read from the start of the observation buffer
+const CONTINUOUS_ACTION: i32 = -1;
+
+/// Plays by running an untrusted WebAssembly module in a sandbox. The module can't import anything and has to export:
+///
+/// * `memory`
+/// * `observation_buffer() -> i32` - where in memory the observation is written before each action, room for
+///   [`OBSERVATION_SIZE`](crate::observation::OBSERVATION_SIZE) little endian `f32`s
+/// * `act(player_id: i32) -> i32` - one of the [`DISCRETE_ACTIONS`] or -1 for a continuous action, which is read as
+///   3 `f32`s from the start of the observation buffer
+///
+/// Running out of fuel, trapping or answering with something invalid fails the action, so the turn is forfeited
+pub struct WasmAgent {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    observation_buffer: TypedFunc<(), i32>,
+    act: TypedFunc<i32, i32>,
+}
+
+impl WasmAgent {
+    pub fn load(wasm: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_LIMIT)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(STARTUP_FUEL)
+            .map_err(|error| error.to_string())?;
+        // nothing is linked, so modules which import anything are rejected
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("The module doesn't export its memory")?;
+        let observation_buffer = instance.get_typed_func(&store, "observation_buffer")?;
+        let act = instance.get_typed_func(&store, "act")?;
+        Ok(Self {
+            store,
+            memory,
+            observation_buffer,
+            act,
+        })
+    }
+}
+
+impl Agent for WasmAgent {
+    fn act(
+        &mut self,
+        player_id: PlayerId,
+        environment: &TagEnvironment,
+    ) -> Result<TagPlayerAction> {
+        self.store
+            .set_fuel(FUEL_PER_STEP)
+            .map_err(|error| error.to_string())?;
+        let buffer = self.observation_buffer.call(&mut self.store, ())? as u32 as usize;
+        let observation: Vec<u8> = observe(environment, player_id)
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        self.memory
+            .write(&mut self.store, buffer, &observation)
+            .map_err(|error| error.to_string())?;
+
+        let action = match self.act.call(&mut self.store, player_id as i32)? {
+            CONTINUOUS_ACTION => {
+                let mut bytes = [0; 12];
+                self.memory
+                    .read(&self.store, buffer, &mut bytes)
+                    .map_err(|error| error.to_string())?;
+                let mut values = bytes
+                    .chunks_exact(4)
+                    .map(|value| f32::from_le_bytes(value.try_into().unwrap()));
+                Action::Continuous([(); 3].map(|_| values.next().unwrap()))
+            }
+            code if (0..DISCRETE_ACTIONS as i32).contains(&code) => Action::Discrete(code as usize),
+            code => return Err(format!("The module chose an invalid action {}", code).into()),
+        };
+        Ok(action.decode(environment, player_id))
+    }
+}
+
+impl fmt::Debug for WasmAgent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmAgent").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Vector2D;
+
+    use super::*;
+
+    fn environment() -> TagEnvironment {
+        test_environment((10., 10.), &[(1., 1.), (1.5, 1.)])
+    }
+
+    fn load(wat: &str) -> Result<WasmAgent> {
+        WasmAgent::load(&wat::parse_str(wat).unwrap())
+    }
+
+    /// Tags when it, otherwise runs directly away from the closest player
+    const CHASER: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "observation_buffer") (result i32) i32.const 16)
+            (func (export "act") (param i32) (result i32)
+                (if (f32.gt (f32.load (i32.const 24)) (f32.const 0))
+                    (then (return (i32.const 9))))
+                (f32.store (i32.const 16) (f32.neg (f32.load (i32.const 40))))
+                (f32.store (i32.const 20) (f32.neg (f32.load (i32.const 44))))
+                (f32.store (i32.const 24) (f32.const -1))
+                (i32.const -1)))
+    "#;
+
+    #[test]
+    fn plays_by_the_module() {
+        let mut agent = load(CHASER).unwrap();
+        let environment = environment();
+        assert_eq!(
+            agent.act(0, &environment).unwrap(),
+            TagPlayerAction::Tag { player_id: 1 }
+        );
+        let TagPlayerAction::Run { stretch } = agent.act(1, &environment).unwrap() else {
+            panic!("expected a run");
+        };
+        assert!((stretch - Vector2D::new(0.1, 0.)).length() < 1e-5);
+    }
+
+    #[test]
+    fn forfeits_overruns() {
+        let environment = environment();
+        let mut endless = load(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "observation_buffer") (result i32) i32.const 0)
+                (func (export "act") (param i32) (result i32) (loop (br 0)) i32.const 0))"#,
+        )
+        .unwrap();
+        assert!(endless.act(0, &environment).is_err());
+        assert!(endless.act(0, &environment).is_err());
+
+        let mut out_of_bounds = load(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "observation_buffer") (result i32) i32.const 65530)
+                (func (export "act") (param i32) (result i32) i32.const 0))"#,
+        )
+        .unwrap();
+        assert!(out_of_bounds.act(0, &environment).is_err());
+
+        // too much memory
+        assert!(load(
+            r#"(module
+                (memory (export "memory") 1000)
+                (func (export "observation_buffer") (result i32) i32.const 0)
+                (func (export "act") (param i32) (result i32) i32.const 0))"#
+        )
+        .is_err());
+        // imports
+        assert!(load(
+            r#"(module
+                (import "env" "exit" (func))
+                (memory (export "memory") 1)
+                (func (export "observation_buffer") (result i32) i32.const 0)
+                (func (export "act") (param i32) (result i32) i32.const 0))"#
+        )
+        .is_err());
+    }
+}